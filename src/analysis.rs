@@ -0,0 +1,196 @@
+//! Aggregations and consistency checks over pools of cards, like the ones deck building and curation tools need.
+
+use crate::{
+    cards::{
+        properties::{Number, Read},
+        Card,
+    },
+    clean_ascii,
+};
+
+/// Counts how many `cards` cost each amount from 0 up to `max_bucket`. The last bucket also counts every card that costs more than `max_bucket`, so the result always has `max_bucket + 1` buckets.
+///
+/// Costs are counted by their assumed value: variable costs like `X` count as 0, and imprecise costs like `>2` count as the smallest cost they allow.
+#[must_use]
+pub fn cost_buckets(cards: &[&Card], max_bucket: usize) -> Vec<usize> {
+    let mut buckets = vec![0; max_bucket + 1];
+    for card in cards {
+        buckets[card.cost.assume().min(max_bucket)] += 1;
+    }
+    buckets
+}
+
+/// Finds the `cards` whose name is the same as one of the `keyword_names`, ignoring casing and diaereses. Such names make searches for the keyword ambiguous.
+#[must_use]
+pub fn cards_named_like_keywords<'a>(cards: &'a [Card], keyword_names: &[&str]) -> Vec<&'a Card> {
+    let keyword_names: Vec<String> = keyword_names.iter().map(|name| clean_ascii(name)).collect();
+    cards
+        .iter()
+        .filter(|card| keyword_names.contains(&clean_ascii(&card.name)))
+        .collect()
+}
+
+/// Finds every pair of `cards` `(a, b)` where `a`'s name is part of `b`'s name but isn't all of it, ignoring casing and diaereses. Such names make autocompletion and `name:` searches for `a` also bring up `b`. Blank names are skipped, since they'd be part of every name.
+#[must_use]
+pub fn name_substring_pairs(cards: &[Card]) -> Vec<(&Card, &Card)> {
+    let names: Vec<String> = cards.iter().map(|card| clean_ascii(&card.name)).collect();
+    let mut pairs = vec![];
+    for (a, a_name) in cards.iter().zip(&names) {
+        if a_name.trim().is_empty() {
+            continue;
+        }
+        for (b, b_name) in cards.iter().zip(&names) {
+            if a_name != b_name && b_name.contains(a_name.as_str()) {
+                pairs.push((a, b));
+            }
+        }
+    }
+    pairs
+}
+
+/// Heuristically checks whether `a` is strictly better than `b`: it costs the same or less, has at least as much power, health and defense, and is cheaper or has more of at least one stat.
+///
+/// Stats are compared by their assumed values. Cards that don't have the same stats, like a command and a creature, can't be compared, so neither dominates the other.
+#[must_use]
+pub fn dominates(a: &Card, b: &Card) -> bool {
+    let cost_a = a.cost.assume();
+    let cost_b = b.cost.assume();
+    if cost_a > cost_b {
+        return false;
+    }
+
+    let mut strictly_better = cost_a < cost_b;
+    for property in [Number::Power, Number::Health, Number::Defense] {
+        let (stat_a, stat_b) = match (a.get_num_property(&property), b.get_num_property(&property))
+        {
+            (Some(stat_a), Some(stat_b)) => (stat_a, stat_b),
+            (None, None) => continue,
+            _ => return false,
+        };
+        match stat_a.assume().cmp(&stat_b.assume()) {
+            std::cmp::Ordering::Less => return false,
+            std::cmp::Ordering::Greater => strictly_better = true,
+            std::cmp::Ordering::Equal => (),
+        }
+    }
+    strictly_better
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cards::Card,
+        numbers::{Comparison, MaybeImprecise, MaybeVar},
+    };
+
+    use super::{cards_named_like_keywords, cost_buckets, dominates, name_substring_pairs};
+
+    #[test]
+    fn test_cost_buckets() {
+        let card = |cost: MaybeImprecise| Card {
+            cost,
+            ..Default::default()
+        };
+        let mut cards: Vec<Card> = (0..=8)
+            .map(|cost| card(MaybeImprecise::Precise(MaybeVar::Const(cost))))
+            .collect();
+        cards.push(card(MaybeImprecise::Precise(MaybeVar::Var('X'))));
+        cards.push(card(MaybeImprecise::Imprecise(Comparison::GreaterThan(2))));
+        cards.push(card(MaybeImprecise::Imprecise(
+            Comparison::GreaterThanOrEqual(10),
+        )));
+        let cards: Vec<&Card> = cards.iter().collect();
+
+        assert_eq!(cost_buckets(&cards, 6), [2, 1, 1, 2, 1, 1, 4]);
+        assert_eq!(cost_buckets(&cards, 0), [12]);
+    }
+
+    #[test]
+    fn test_cards_named_like_keywords() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Flying"),
+            card("Flying Ant"),
+            card("Devours"),
+            card("Bee"),
+        ];
+
+        let flagged: Vec<&str> = cards_named_like_keywords(&cards, &["flying", "devours"])
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(flagged, ["Flying", "Devours"]);
+        assert!(cards_named_like_keywords(&cards, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_dominates() {
+        let stat = |x: usize| MaybeImprecise::Precise(MaybeVar::Const(x));
+        let creature = |cost: usize, power: usize, health: usize| Card {
+            r#type: "creature".to_string(),
+            cost: stat(cost),
+            power: stat(power),
+            health: stat(health),
+            ..Default::default()
+        };
+
+        let ant = creature(2, 1, 2);
+        let better_ant = creature(2, 2, 2);
+        let cheap_ant = creature(1, 1, 2);
+        let glass_cannon = creature(2, 3, 1);
+
+        assert!(dominates(&better_ant, &ant));
+        assert!(dominates(&cheap_ant, &ant));
+        assert!(!dominates(&ant, &better_ant));
+        assert!(!dominates(&ant, &ant));
+        assert!(!dominates(&glass_cannon, &ant));
+        assert!(!dominates(&ant, &glass_cannon));
+
+        let command = Card {
+            r#type: "command".to_string(),
+            cost: stat(1),
+            ..Default::default()
+        };
+        assert!(!dominates(&command, &ant));
+        assert!(!dominates(&ant, &command));
+
+        let cheap_command = Card {
+            cost: stat(0),
+            ..command.clone()
+        };
+        assert!(dominates(&cheap_command, &command));
+    }
+
+    #[test]
+    fn test_name_substring_pairs() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Green Queen"),
+            card("Queen"),
+            card("Bee"),
+            card("queen"),
+            card("Beetle"),
+            card(""),
+            card(" "),
+        ];
+
+        let pairs: Vec<(&str, &str)> = name_substring_pairs(&cards)
+            .iter()
+            .map(|(a, b)| (a.name.as_str(), b.name.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("Queen", "Green Queen"),
+                ("Bee", "Beetle"),
+                ("queen", "Green Queen")
+            ]
+        );
+    }
+}
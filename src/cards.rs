@@ -4,12 +4,17 @@ use crate::cards::properties::Array;
 use crate::cards::properties::Number;
 use crate::cards::properties::Read;
 use crate::cards::properties::Text;
+use crate::clean_ascii;
 use crate::clean_ascii_keep_case;
 use crate::numbers::MaybeImprecise;
 use rand::prelude::SliceRandom;
+use rand::rngs::StdRng;
 use rand::thread_rng;
+use rand::Rng;
+use rand::SeedableRng;
 use rich_text::RichString;
 use std::{collections::HashMap, fmt::Display};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use serde::{Deserialize, Serialize};
 
@@ -40,6 +45,8 @@ pub struct Card {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub images: Vec<Image>,
     /// The card's text, excluding cost, stats and typeline.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "RichString::is_empty")]
     pub description: RichString,
     /// The card's blood cost.
     pub cost: MaybeImprecise,
@@ -57,7 +64,8 @@ pub struct Card {
     pub keywords: Vec<Keyword>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    /// Kins of the card, must include parent kins.
+    #[serde(deserialize_with = "deserialize_kins")]
+    /// Kins of the card, must include parent kins. May be read from a single string.
     pub kins: Vec<String>,
     #[serde(default)]
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -85,12 +93,20 @@ pub struct Card {
 
 impl Display for Card {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut name = self.name.as_str();
-        if name.len() > 20 {
-            name = &name[0..18];
+        let mut nameline = String::new();
+        let mut width = 0;
+        if self.name.width() > 20 {
+            for char in self.name.chars() {
+                width += char.width().unwrap_or(0);
+                if width > 18 {
+                    break;
+                }
+                nameline.push(char);
+            }
+        } else {
+            nameline.push_str(&self.name);
         }
-        let mut nameline = name.to_owned();
-        for _ in nameline.len()..24 {
+        for _ in nameline.width()..24 {
             nameline.push(' ');
         }
         nameline.push_str(&self.cost.to_string());
@@ -100,6 +116,48 @@ impl Display for Card {
     }
 }
 
+/// Kins as they may be written in card data: a single kin, or an array of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Kins {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl From<Kins> for Vec<String> {
+    fn from(kins: Kins) -> Self {
+        match kins {
+            Kins::One(kin) => vec![kin],
+            Kins::Many(kins) => kins,
+        }
+    }
+}
+
+fn deserialize_kins<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<String>, D::Error> {
+    Kins::deserialize(deserializer).map(Into::into)
+}
+
+fn deserialize_optional_kins<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error> {
+    Option::<Kins>::deserialize(deserializer).map(|kins| kins.map(Into::into))
+}
+
+/// Whether cards of this type have power, health and defense. Commands, including extended commands and command sagas, don't.
+#[must_use]
+pub fn type_has_combat_stats(r#type: &str) -> bool {
+    !type_has_word(r#type, "command")
+}
+
+/// Whether one of the words in `r#type` is `word`, ignoring casing.
+fn type_has_word(r#type: &str, word: &str) -> bool {
+    r#type
+        .split_whitespace()
+        .any(|type_word| type_word.eq_ignore_ascii_case(word))
+}
+
 impl Read for Card {
     fn get_flavor_text(&self) -> Option<&str> {
         Some(&self.flavor_text)
@@ -110,24 +168,24 @@ impl Read for Card {
         match property {
             Number::Cost => Some(self.cost.clone()),
             Number::Health => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.health.clone())
+                } else {
+                    None
                 }
             }
             Number::Defense => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.defense.clone())
+                } else {
+                    None
                 }
             }
             Number::Power => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.power.clone())
+                } else {
+                    None
                 }
             }
         }
@@ -137,11 +195,12 @@ impl Read for Card {
     /// Always returns Some.
     fn get_text_property(&self, property: &Text) -> Option<String> {
         Some(match property {
-            Text::Id => self.id.to_string(),
-            Text::Name => self.name.to_string(),
-            Text::Type => self.r#type.to_string(),
+            Text::Id => self.id.clone(),
+            Text::Name => self.name.clone(),
+            Text::Type => self.r#type.clone(),
             Text::Description => self.description.to_string(),
-            Text::FlavorText => self.flavor_text.to_string(),
+            Text::FlavorText => self.flavor_text.clone(),
+            Text::Set => self.set.clone(),
         })
     }
 
@@ -151,6 +210,8 @@ impl Read for Card {
         Some(match property {
             Array::Functions => &self.functions,
             Array::Kins => &self.kins,
+            Array::Abilities => &self.abilities,
+            Array::Other => &self.other,
             // Array::Artists => &self.artists,
         })
     }
@@ -181,6 +242,15 @@ impl Read for Card {
     fn get_kins(&self) -> Option<&[String]> {
         Some(&self.kins)
     }
+
+    /// Return a card's images. Always returns Some. If the card has no images, it will return Some empty array.
+    fn get_images(&self) -> Option<&[Image]> {
+        Some(&self.images)
+    }
+
+    fn get_legality(&self) -> Option<&HashMap<String, String>> {
+        Some(&self.legality)
+    }
 }
 
 impl Read for &Card {
@@ -193,24 +263,24 @@ impl Read for &Card {
         match property {
             Number::Cost => Some(self.cost.clone()),
             Number::Health => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.health.clone())
+                } else {
+                    None
                 }
             }
             Number::Defense => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.defense.clone())
+                } else {
+                    None
                 }
             }
             Number::Power => {
-                if self.r#type.contains("command") {
-                    None
-                } else {
+                if type_has_combat_stats(&self.r#type) {
                     Some(self.power.clone())
+                } else {
+                    None
                 }
             }
         }
@@ -220,11 +290,12 @@ impl Read for &Card {
     /// Always returns Some.
     fn get_text_property(&self, property: &Text) -> Option<String> {
         Some(match property {
-            Text::Id => self.id.to_string(),
-            Text::Name => self.name.to_string(),
-            Text::Type => self.r#type.to_string(),
+            Text::Id => self.id.clone(),
+            Text::Name => self.name.clone(),
+            Text::Type => self.r#type.clone(),
             Text::Description => self.description.to_string(),
-            Text::FlavorText => self.flavor_text.to_string(),
+            Text::FlavorText => self.flavor_text.clone(),
+            Text::Set => self.set.clone(),
         })
     }
 
@@ -234,6 +305,8 @@ impl Read for &Card {
         Some(match property {
             Array::Functions => &self.functions,
             Array::Kins => &self.kins,
+            Array::Abilities => &self.abilities,
+            Array::Other => &self.other,
             // Array::Artists => &self.artists,
         })
     }
@@ -264,6 +337,15 @@ impl Read for &Card {
     fn get_kins(&self) -> Option<&[String]> {
         Some(&self.kins)
     }
+
+    /// Return a card's images. Always returns Some. If the card has no images, it will return Some empty array.
+    fn get_images(&self) -> Option<&[Image]> {
+        Some(&self.images)
+    }
+
+    fn get_legality(&self) -> Option<&HashMap<String, String>> {
+        Some(&self.legality)
+    }
 }
 
 impl Read for CardId {
@@ -274,21 +356,33 @@ impl Read for CardId {
         match property {
             Number::Cost => self.cost.clone(),
             Number::Health => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.health.clone()
                 }
             }
             Number::Defense => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.defense.clone()
                 }
             }
             Number::Power => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.power.clone()
@@ -302,7 +396,7 @@ impl Read for CardId {
             Text::Name => self.name.as_deref().map(ToString::to_string),
             Text::Type => self.r#type.as_deref().map(ToString::to_string),
             Text::Description => self.description.as_ref().map(ToString::to_string),
-            Text::FlavorText | Text::Id => None,
+            Text::FlavorText | Text::Id | Text::Set => None,
         }
     }
 
@@ -310,6 +404,8 @@ impl Read for CardId {
         match property {
             Array::Functions => self.functions.as_deref(),
             Array::Kins => self.kins.as_deref(),
+            Array::Abilities => self.abilities.as_deref(),
+            Array::Other => None,
             // Array::Artists => None,
         }
     }
@@ -343,21 +439,33 @@ impl Read for &CardId {
         match property {
             Number::Cost => self.cost.clone(),
             Number::Health => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.health.clone()
                 }
             }
             Number::Defense => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.defense.clone()
                 }
             }
             Number::Power => {
-                if self.r#type.as_ref().is_some_and(|x| x.contains("command")) {
+                if self
+                    .r#type
+                    .as_deref()
+                    .is_some_and(|x| !type_has_combat_stats(x))
+                {
                     None
                 } else {
                     self.power.clone()
@@ -371,7 +479,7 @@ impl Read for &CardId {
             Text::Name => self.name.as_deref().map(ToString::to_string),
             Text::Type => self.r#type.as_deref().map(ToString::to_string),
             Text::Description => self.description.as_ref().map(ToString::to_string),
-            Text::FlavorText | Text::Id => None,
+            Text::FlavorText | Text::Id | Text::Set => None,
         }
     }
 
@@ -379,6 +487,8 @@ impl Read for &CardId {
         match property {
             Array::Functions => self.functions.as_deref(),
             Array::Kins => self.kins.as_deref(),
+            Array::Abilities => self.abilities.as_deref(),
+            Array::Other => None,
             // Array::Artists => None,
         }
     }
@@ -405,7 +515,7 @@ impl Read for &CardId {
 }
 
 /// Data structure for card identities. These card identities are slightly more general than the concept within the game, as they allow you to match things that are only relevant for searching cards.
-#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct CardId {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -420,6 +530,7 @@ pub struct CardId {
     pub r#type: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
+    #[serde(deserialize_with = "deserialize_optional_kins")]
     pub kins: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub health: Option<MaybeImprecise>,
@@ -436,6 +547,7 @@ pub struct CardId {
 }
 
 /// A keyword may contain data. This data may be a string or a `CardId`.
+#[allow(clippy::large_enum_variant)]
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 #[serde(tag = "type")]
 pub enum KeywordData {
@@ -451,11 +563,97 @@ pub struct Keyword {
     pub data: Option<KeywordData>,
 }
 
+/// The kinds of data a keyword may carry.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordDataKind {
+    None,
+    String,
+    CardId,
+}
+
+impl KeywordData {
+    #[must_use]
+    pub const fn kind(&self) -> KeywordDataKind {
+        match self {
+            Self::CardId(_) => KeywordDataKind::CardId,
+            Self::String(_) => KeywordDataKind::String,
+        }
+    }
+}
+
+/// The kind of data each keyword is expected to carry. Keywords it doesn't know about aren't checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeywordSchema {
+    kinds: HashMap<String, KeywordDataKind>,
+}
+
+impl KeywordSchema {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes keywords named `name`, ignoring casing and diaereses, expect data of this `kind`.
+    pub fn insert(&mut self, name: &str, kind: KeywordDataKind) {
+        self.kinds.insert(clean_ascii(name), kind);
+    }
+
+    /// Returns the kind of data keywords named `name` expect, if the schema knows about them.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<KeywordDataKind> {
+        self.kinds.get(&clean_ascii(name)).copied()
+    }
+}
+
+/// A keyword carries a kind of data different from the one its schema expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeywordDataError {
+    pub keyword: String,
+    pub expected: KeywordDataKind,
+    pub found: KeywordDataKind,
+}
+
+impl Keyword {
+    /// Returns the kind of data the keyword carries.
+    #[must_use]
+    pub fn data_kind(&self) -> KeywordDataKind {
+        self.data
+            .as_ref()
+            .map_or(KeywordDataKind::None, KeywordData::kind)
+    }
+
+    /// Checks that the keyword carries the kind of data the `schema` expects for it.
+    /// # Errors
+    /// When the `schema` knows the keyword and expects a different kind of data.
+    pub fn validate(&self, schema: &KeywordSchema) -> Result<(), KeywordDataError> {
+        let found = self.data_kind();
+        match schema.get(&self.name) {
+            Some(expected) if expected != found => Err(KeywordDataError {
+                keyword: self.name.clone(),
+                expected,
+                found,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
 impl Card {
+    /// Creates a builder for a card where every field starts out with its default value.
+    #[must_use]
+    pub fn builder() -> CardBuilder {
+        CardBuilder::default()
+    }
+
     /// Obtains a randomly selected image name from the `Card`'s img field. If it can't, it gets an image name based on its name.
     #[must_use]
     pub fn get_random_image_path(&self) -> String {
-        let rng = &mut rand::thread_rng();
+        self.get_random_image_path_with(&mut thread_rng())
+    }
+
+    /// Does the same as `get_random_image_path`, but picks the image with the given `rng`.
+    #[must_use]
+    pub fn get_random_image_path_with(&self, rng: &mut impl Rng) -> String {
         self.images
             .last()
             .and_then(|x| match &x.sources {
@@ -469,16 +667,28 @@ impl Card {
     /// Obtains the image matching the index. Gets the image matching the name if there's no image for that index in self.images
     #[must_use]
     pub fn get_image_path(&self, index: usize) -> String {
+        self.get_image_path_with(index, &mut thread_rng())
+    }
+
+    /// Does the same as `get_image_path`, but picks among the image's files with the given `rng`.
+    #[must_use]
+    pub fn get_image_path_with(&self, index: usize, rng: &mut impl Rng) -> String {
         self.images
             .get(index)
             .and_then(|x| match &x.sources {
-                ImageSource::Files(files) => files.choose(&mut thread_rng()),
+                ImageSource::Files(files) => files.choose(rng),
                 ImageSource::CardName => None,
             })
             .cloned()
             .unwrap_or_else(|| self.get_name_image_path())
     }
 
+    /// Does the same as `get_image_path`, but always picks the same file for the same `seed`, for reproducible renders.
+    #[must_use]
+    pub fn get_image_path_seeded(&self, index: usize, seed: u64) -> String {
+        self.get_image_path_with(index, &mut StdRng::seed_from_u64(seed))
+    }
+
     #[must_use]
     pub fn get_name_image_path(&self) -> String {
         clean_ascii_keep_case(&self.name.replace(' ', ""))
@@ -491,9 +701,106 @@ impl Card {
             .flat_map(|x| -> &[String] { x.authors.as_ref() })
             .collect()
     }
+
+    /// Whether one of the words in the card's type is `word`, ignoring casing.
+    fn has_type_word(&self, word: &str) -> bool {
+        type_has_word(&self.r#type, word)
+    }
+
+    /// Whether the card is a command, including extended commands and command sagas.
+    #[must_use]
+    pub fn is_command(&self) -> bool {
+        !type_has_combat_stats(&self.r#type)
+    }
+
+    #[must_use]
+    pub fn is_creature(&self) -> bool {
+        self.has_type_word("creature")
+    }
+
+    /// Whether the card is a saga of any kind, like a creature saga or a command saga.
+    #[must_use]
+    pub fn is_saga(&self) -> bool {
+        self.has_type_word("saga")
+    }
+
+    #[must_use]
+    pub fn is_vestige(&self) -> bool {
+        self.has_type_word("vestige")
+    }
+
+    /// Returns the names of the card's keywords, in order.
+    #[must_use]
+    pub fn keyword_names(&self) -> Vec<&str> {
+        self.keywords
+            .iter()
+            .map(|keyword| keyword.name.as_str())
+            .collect()
+    }
+
+    /// Returns whether the card has a keyword with exactly this `name`.
+    #[must_use]
+    pub fn has_keyword(&self, name: &str) -> bool {
+        self.keywords.iter().any(|keyword| keyword.name == name)
+    }
+
+    /// Returns a copy of the card with the variables in its stats replaced by the numbers they're bound to in `bindings`.
+    #[must_use]
+    pub fn with_variables(&self, bindings: &HashMap<char, usize>) -> Self {
+        Self {
+            cost: self.cost.substitute(bindings),
+            health: self.health.substitute(bindings),
+            defense: self.defense.substitute(bindings),
+            power: self.power.substitute(bindings),
+            ..self.clone()
+        }
+    }
+
+    /// Overwrites the card's fields with the ones that are present in `overrides`. Fields that are `None` in `overrides` are left untouched.
+    pub fn apply_overrides(&mut self, overrides: &CardId) {
+        if let Some(name) = &overrides.name {
+            self.name.clone_from(name);
+        }
+        if let Some(r#type) = &overrides.r#type {
+            self.r#type.clone_from(r#type);
+        }
+        if let Some(description) = &overrides.description {
+            self.description.clone_from(description);
+        }
+        if let Some(cost) = &overrides.cost {
+            self.cost.clone_from(cost);
+        }
+        if let Some(health) = &overrides.health {
+            self.health.clone_from(health);
+        }
+        if let Some(defense) = &overrides.defense {
+            self.defense.clone_from(defense);
+        }
+        if let Some(power) = &overrides.power {
+            self.power.clone_from(power);
+        }
+        if let Some(keywords) = &overrides.keywords {
+            self.keywords.clone_from(keywords);
+        }
+        if let Some(kins) = &overrides.kins {
+            self.kins.clone_from(kins);
+        }
+        if let Some(abilities) = &overrides.abilities {
+            self.abilities.clone_from(abilities);
+        }
+        if let Some(functions) = &overrides.functions {
+            self.functions.clone_from(functions);
+        }
+    }
 }
 
 impl CardId {
+    /// Creates a builder for an identity where every field starts out as `None`.
+    #[must_use]
+    pub fn builder() -> CardIdBuilder {
+        CardIdBuilder::default()
+    }
+
     #[must_use]
     /// Creates a vector of `QueryRestriction`s defined by the `CardId`.
     pub fn get_as_query(&self) -> Vec<QueryRestriction> {
@@ -546,3 +853,628 @@ impl CardId {
         restrictions
     }
 }
+
+/// Builds up a `Card` one field at a time. Fields that are never set keep their default value.
+#[derive(Debug, Clone, Default)]
+pub struct CardBuilder {
+    card: Card,
+}
+
+impl CardBuilder {
+    #[must_use]
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.card.id = id.into();
+        self
+    }
+
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.card.name = name.into();
+        self
+    }
+
+    #[must_use]
+    pub fn type_(mut self, r#type: impl Into<String>) -> Self {
+        self.card.r#type = r#type.into();
+        self
+    }
+
+    #[must_use]
+    pub fn description(mut self, description: RichString) -> Self {
+        self.card.description = description;
+        self
+    }
+
+    #[must_use]
+    pub const fn cost(mut self, cost: MaybeImprecise) -> Self {
+        self.card.cost = cost;
+        self
+    }
+
+    #[must_use]
+    pub const fn health(mut self, health: MaybeImprecise) -> Self {
+        self.card.health = health;
+        self
+    }
+
+    #[must_use]
+    pub const fn defense(mut self, defense: MaybeImprecise) -> Self {
+        self.card.defense = defense;
+        self
+    }
+
+    #[must_use]
+    pub const fn power(mut self, power: MaybeImprecise) -> Self {
+        self.card.power = power;
+        self
+    }
+
+    #[must_use]
+    pub fn set(mut self, set: impl Into<String>) -> Self {
+        self.card.set = set.into();
+        self
+    }
+
+    #[must_use]
+    pub fn flavor_text(mut self, flavor_text: impl Into<String>) -> Self {
+        self.card.flavor_text = flavor_text.into();
+        self
+    }
+
+    /// Adds an image to the card's images.
+    #[must_use]
+    pub fn image(mut self, image: Image) -> Self {
+        self.card.images.push(image);
+        self
+    }
+
+    /// Adds a kin to the card's kins.
+    #[must_use]
+    pub fn kin(mut self, kin: impl Into<String>) -> Self {
+        self.card.kins.push(kin.into());
+        self
+    }
+
+    /// Adds a keyword to the card's keywords.
+    #[must_use]
+    pub fn keyword(mut self, keyword: Keyword) -> Self {
+        self.card.keywords.push(keyword);
+        self
+    }
+
+    /// Adds an ability to the card's abilities.
+    #[must_use]
+    pub fn ability(mut self, ability: impl Into<String>) -> Self {
+        self.card.abilities.push(ability.into());
+        self
+    }
+
+    /// Adds a function to the card's functions.
+    #[must_use]
+    pub fn function(mut self, function: impl Into<String>) -> Self {
+        self.card.functions.push(function.into());
+        self
+    }
+
+    /// Adds a tag to the card's other tags.
+    #[must_use]
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.card.other.push(tag.into());
+        self
+    }
+
+    /// Sets the card's legality in a `format`.
+    #[must_use]
+    pub fn legality(mut self, format: impl Into<String>, status: impl Into<String>) -> Self {
+        self.card.legality.insert(format.into(), status.into());
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Card {
+        self.card
+    }
+}
+
+/// Builds up a `CardId` one field at a time. Fields that are never set stay `None`.
+#[derive(Debug, Clone, Default)]
+pub struct CardIdBuilder {
+    id: CardId,
+}
+
+impl CardIdBuilder {
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.id.name = Some(name.into());
+        self
+    }
+
+    #[must_use]
+    pub fn type_(mut self, r#type: impl Into<String>) -> Self {
+        self.id.r#type = Some(r#type.into());
+        self
+    }
+
+    #[must_use]
+    pub fn description(mut self, description: RichString) -> Self {
+        self.id.description = Some(description);
+        self
+    }
+
+    #[must_use]
+    pub const fn cost(mut self, cost: MaybeImprecise) -> Self {
+        self.id.cost = Some(cost);
+        self
+    }
+
+    #[must_use]
+    pub const fn health(mut self, health: MaybeImprecise) -> Self {
+        self.id.health = Some(health);
+        self
+    }
+
+    #[must_use]
+    pub const fn defense(mut self, defense: MaybeImprecise) -> Self {
+        self.id.defense = Some(defense);
+        self
+    }
+
+    #[must_use]
+    pub const fn power(mut self, power: MaybeImprecise) -> Self {
+        self.id.power = Some(power);
+        self
+    }
+
+    /// Adds a kin to the identity's kins.
+    #[must_use]
+    pub fn kin(mut self, kin: impl Into<String>) -> Self {
+        self.id.kins.get_or_insert_with(Vec::new).push(kin.into());
+        self
+    }
+
+    /// Adds a keyword to the identity's keywords.
+    #[must_use]
+    pub fn keyword(mut self, keyword: Keyword) -> Self {
+        self.id.keywords.get_or_insert_with(Vec::new).push(keyword);
+        self
+    }
+
+    /// Adds an ability to the identity's abilities.
+    #[must_use]
+    pub fn ability(mut self, ability: impl Into<String>) -> Self {
+        self.id
+            .abilities
+            .get_or_insert_with(Vec::new)
+            .push(ability.into());
+        self
+    }
+
+    /// Adds a function to the identity's functions.
+    #[must_use]
+    pub fn function(mut self, function: impl Into<String>) -> Self {
+        self.id
+            .functions
+            .get_or_insert_with(Vec::new)
+            .push(function.into());
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> CardId {
+        self.id
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rand::{rngs::StdRng, SeedableRng};
+    use unicode_width::UnicodeWidthStr;
+
+    use crate::{
+        numbers::{MaybeImprecise, MaybeVar},
+        search::{matches_query, Cache, Query, Sort, Ternary},
+    };
+
+    use super::{
+        properties::{Array, Number, Read},
+        rich_text::{RichElement, RichString},
+        type_has_combat_stats, Card, CardId, Image, ImageSource, Keyword, KeywordData,
+        KeywordDataError, KeywordDataKind, KeywordSchema,
+    };
+
+    #[test]
+    fn test_apply_overrides() {
+        let mut card = Card {
+            id: "base".to_string(),
+            name: "Base Card".to_string(),
+            r#type: "creature".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(2)),
+            power: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            kins: vec!["insect".to_string()],
+            ..Default::default()
+        };
+        let overrides = CardId {
+            name: Some("Carta Base".to_string()),
+            cost: Some(MaybeImprecise::Precise(MaybeVar::Const(4))),
+            description: None,
+            keywords: None,
+            r#type: None,
+            kins: None,
+            health: None,
+            defense: None,
+            power: None,
+            abilities: None,
+            functions: None,
+        };
+
+        card.apply_overrides(&overrides);
+
+        assert_eq!(card.name, "Carta Base");
+        assert_eq!(card.cost, MaybeImprecise::Precise(MaybeVar::Const(4)));
+        assert_eq!(card.id, "base");
+        assert_eq!(card.r#type, "creature");
+        assert_eq!(card.power, MaybeImprecise::Precise(MaybeVar::Const(3)));
+        assert_eq!(card.kins, vec!["insect".to_string()]);
+    }
+
+    #[test]
+    fn test_with_variables() {
+        let card = Card {
+            cost: MaybeImprecise::Precise(MaybeVar::Var('X')),
+            power: MaybeImprecise::Precise(MaybeVar::Var('X')),
+            health: MaybeImprecise::Precise(MaybeVar::Var('Y')),
+            defense: MaybeImprecise::Precise(MaybeVar::Const(1)),
+            ..Default::default()
+        };
+        let card = card.with_variables(&HashMap::from([('X', 3)]));
+
+        assert_eq!(card.cost, MaybeImprecise::Precise(MaybeVar::Const(3)));
+        assert_eq!(card.power, MaybeImprecise::Precise(MaybeVar::Const(3)));
+        assert_eq!(card.health, MaybeImprecise::Precise(MaybeVar::Var('Y')));
+        assert_eq!(card.defense, MaybeImprecise::Precise(MaybeVar::Const(1)));
+    }
+
+    #[test]
+    fn test_card_id_builder() {
+        let id = CardId::builder()
+            .name("Ant")
+            .cost(MaybeImprecise::Precise(MaybeVar::Const(1)))
+            .build();
+        assert_eq!(
+            id,
+            CardId {
+                name: Some("Ant".to_string()),
+                cost: Some(MaybeImprecise::Precise(MaybeVar::Const(1))),
+                ..Default::default()
+            }
+        );
+
+        let ant = Card {
+            name: "Ant".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(1)),
+            ..Default::default()
+        };
+        let expensive_ant = Card {
+            cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..ant.clone()
+        };
+        let query = Query {
+            name: String::new(),
+            restrictions: id.get_as_query(),
+            sort: Sort::None,
+        };
+        let cards = [ant, expensive_ant];
+        let cache = Cache::default();
+        assert_eq!(
+            matches_query(&cards[0], &query, &cards.iter(), &cache),
+            Ternary::True
+        );
+        assert_eq!(
+            matches_query(&cards[1], &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+    }
+
+    #[test]
+    fn test_keyword_names() {
+        let card = Card {
+            keywords: vec![
+                Keyword {
+                    name: "flying".to_string(),
+                    data: None,
+                },
+                Keyword {
+                    name: "devours".to_string(),
+                    data: Some(KeywordData::CardId(CardId::builder().name("Ant").build())),
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(card.keyword_names(), ["flying", "devours"]);
+        assert!(Card::default().keyword_names().is_empty());
+
+        assert!(card.has_keyword("flying"));
+        assert!(card.has_keyword("devours"));
+        assert!(!card.has_keyword("fly"));
+        assert!(!card.has_keyword("Ant"));
+    }
+
+    #[test]
+    fn test_display_num_property() {
+        let command = Card {
+            r#type: "command".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(2)),
+            ..Default::default()
+        };
+        let creature = Card {
+            r#type: "creature".to_string(),
+            power: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..Default::default()
+        };
+        let variable = Card {
+            r#type: "creature".to_string(),
+            power: MaybeImprecise::Precise(MaybeVar::Var('X')),
+            ..Default::default()
+        };
+
+        assert_eq!(command.display_num_property(&Number::Power), "—");
+        assert_eq!(command.display_num_property(&Number::Cost), "2");
+        assert_eq!(creature.display_num_property(&Number::Power), "3");
+        assert_eq!(variable.display_num_property(&Number::Power), "X");
+        assert_eq!(CardId::default().display_num_property(&Number::Power), "—");
+    }
+
+    #[test]
+    fn test_type_has_combat_stats() {
+        assert!(type_has_combat_stats("creature"));
+        assert!(type_has_combat_stats("creature saga"));
+        assert!(!type_has_combat_stats("command"));
+        assert!(!type_has_combat_stats("extended command saga"));
+        assert!(!type_has_combat_stats("Command"));
+        assert!(type_has_combat_stats("commander"));
+    }
+
+    #[test]
+    fn test_type_predicates() {
+        let card = |r#type: &str| Card {
+            r#type: r#type.to_string(),
+            ..Default::default()
+        };
+        let predicates = |card: &Card| {
+            [
+                card.is_command(),
+                card.is_creature(),
+                card.is_saga(),
+                card.is_vestige(),
+            ]
+        };
+
+        assert_eq!(predicates(&card("creature")), [false, true, false, false]);
+        assert_eq!(predicates(&card("Command")), [true, false, false, false]);
+        assert_eq!(
+            predicates(&card("extended command saga")),
+            [true, false, true, false]
+        );
+        assert_eq!(
+            predicates(&card("creature saga")),
+            [false, true, true, false]
+        );
+        assert_eq!(predicates(&card("vestige")), [false, false, false, true]);
+        assert_eq!(predicates(&card("")), [false, false, false, false]);
+        assert_eq!(predicates(&card("creatures")), [false, false, false, false]);
+    }
+
+    #[test]
+    fn test_display_alignment() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..Default::default()
+        };
+        let cost_column = |card: &Card| {
+            let shown = card.to_string();
+            let nameline = shown.lines().next().expect("no nameline").to_owned();
+            let cost = nameline.rfind('3').expect("no cost");
+            nameline[..cost].width()
+        };
+
+        assert_eq!(cost_column(&card("Ant")), 24);
+        assert_eq!(cost_column(&card("Abejá Reína")), 24);
+        assert_eq!(cost_column(&card("Ñandú de las Pampas Húmedas")), 24);
+        assert!(card("Ñandú de las Pampas Húmedas")
+            .to_string()
+            .starts_with("Ñandú de las Pampa "));
+    }
+
+    #[test]
+    fn test_kins_as_vec_property() {
+        let card = Card {
+            kins: vec!["piezan".to_string(), "red kingdom".to_string()],
+            ..Default::default()
+        };
+        let expected = ["piezan".to_string(), "red kingdom".to_string()];
+        assert_eq!(card.get_vec_property(&Array::Kins), Some(&expected[..]));
+        assert_eq!(
+            <&Card as Read>::get_vec_property(&&card, &Array::Kins),
+            Some(&expected[..])
+        );
+
+        let card_id = CardId::builder().kin("piezan").kin("red kingdom").build();
+        assert_eq!(card_id.get_vec_property(&Array::Kins), Some(&expected[..]));
+        assert_eq!(
+            <&CardId as Read>::get_vec_property(&&card_id, &Array::Kins),
+            Some(&expected[..])
+        );
+        assert_eq!(CardId::default().get_vec_property(&Array::Kins), None);
+    }
+
+    #[test]
+    fn test_empty_description_round_trip() {
+        let card = Card {
+            id: "shuffle".to_string(),
+            name: "Shuffle".to_string(),
+            r#type: "command".to_string(),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&card).expect("couldn't serialize card");
+        assert!(json.get("description").is_none(), "{json}");
+
+        let read: Card = serde_json::from_value(json).expect("couldn't deserialize card");
+        assert_eq!(read.description, card.description);
+        assert!(read.description.is_empty());
+
+        let card = Card {
+            description: RichString {
+                elements: vec![RichElement::String(String::new())],
+            },
+            ..card
+        };
+        let json = serde_json::to_value(&card).expect("couldn't serialize card");
+        assert!(json.get("description").is_none(), "{json}");
+    }
+
+    #[test]
+    fn test_keyword_schema() {
+        let mut schema = KeywordSchema::new();
+        schema.insert("devours", KeywordDataKind::CardId);
+        schema.insert("Flying", KeywordDataKind::None);
+
+        let keyword = |name: &str, data: Option<KeywordData>| Keyword {
+            name: name.to_string(),
+            data,
+        };
+        let toad = KeywordData::CardId(CardId {
+            name: Some("Toad".to_string()),
+            ..Default::default()
+        });
+
+        assert_eq!(keyword("devours", Some(toad)).validate(&schema), Ok(()));
+        assert_eq!(keyword("flying", None).validate(&schema), Ok(()));
+        assert_eq!(
+            keyword("mark", Some(KeywordData::String("green".to_string()))).validate(&schema),
+            Ok(())
+        );
+        assert_eq!(
+            keyword("devours", Some(KeywordData::String("Toad".to_string()))).validate(&schema),
+            Err(KeywordDataError {
+                keyword: "devours".to_string(),
+                expected: KeywordDataKind::CardId,
+                found: KeywordDataKind::String,
+            })
+        );
+        assert_eq!(
+            keyword("devours", None).validate(&schema),
+            Err(KeywordDataError {
+                keyword: "devours".to_string(),
+                expected: KeywordDataKind::CardId,
+                found: KeywordDataKind::None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_kins_from_string_or_array() {
+        let card = |kins: &str| -> Card {
+            serde_json::from_str(&format!(
+                r#"{{"id": "ant", "name": "Ant", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {{}}, "kins": {kins}}}"#
+            ))
+            .expect("couldn't read card")
+        };
+        assert_eq!(card(r#""insect""#).kins, ["insect"]);
+        assert_eq!(card(r#"["ant", "insect"]"#).kins, ["ant", "insect"]);
+        assert!(serde_json::from_str::<Card>(r#"{"id": "ant", "name": "Ant", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {}, "kins": 3}"#).is_err());
+
+        let id: CardId = serde_json::from_str(r#"{"kins": "insect"}"#).expect("couldn't read id");
+        assert_eq!(id.kins, Some(vec!["insect".to_string()]));
+        let id: CardId =
+            serde_json::from_str(r#"{"kins": ["ant", "insect"]}"#).expect("couldn't read id");
+        assert_eq!(id.kins, Some(vec!["ant".to_string(), "insect".to_string()]));
+        let id: CardId = serde_json::from_str("{}").expect("couldn't read id");
+        assert_eq!(id.kins, None);
+
+        let serialized = serde_json::to_value(card(r#""insect""#)).expect("couldn't write card");
+        assert_eq!(serialized["kins"], serde_json::json!(["insect"]));
+    }
+
+    #[test]
+    fn test_seeded_image_paths() {
+        let files: Vec<String> = (0..16).map(|i| format!("ant{i}")).collect();
+        let card = |sources: ImageSource| Card {
+            name: "Army Ant".to_string(),
+            images: vec![Image {
+                sources,
+                authors: vec![],
+            }],
+            ..Default::default()
+        };
+        let ant = card(ImageSource::Files(files.clone()));
+
+        for seed in 0..8 {
+            let path = ant.get_image_path_seeded(0, seed);
+            assert!(files.contains(&path));
+            assert_eq!(path, ant.get_image_path_seeded(0, seed));
+        }
+        assert_eq!(ant.get_image_path_seeded(1, 0), "ArmyAnt");
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let first = ant.get_random_image_path_with(&mut rng);
+        let mut rng = StdRng::seed_from_u64(7);
+        assert_eq!(ant.get_random_image_path_with(&mut rng), first);
+
+        let empty = card(ImageSource::Files(vec![]));
+        assert_eq!(empty.get_image_path_seeded(0, 3), "ArmyAnt");
+        assert_eq!(empty.get_random_image_path(), "ArmyAnt");
+    }
+
+    #[test]
+    fn test_card_builder() {
+        let stat = |x: usize| MaybeImprecise::Precise(MaybeVar::Const(x));
+        let built = Card::builder()
+            .id("toad")
+            .name("Toad")
+            .type_("creature")
+            .cost(stat(2))
+            .health(stat(2))
+            .power(stat(1))
+            .kin("amphibian")
+            .keyword(Keyword {
+                name: "devours".to_string(),
+                data: Some(KeywordData::CardId(CardId::builder().name("Fly").build())),
+            })
+            .set("Core")
+            .tag("promo")
+            .legality("standard", "legal")
+            .build();
+
+        let manual = Card {
+            id: "toad".to_string(),
+            name: "Toad".to_string(),
+            r#type: "creature".to_string(),
+            cost: stat(2),
+            health: stat(2),
+            defense: stat(0),
+            power: stat(1),
+            kins: vec!["amphibian".to_string()],
+            keywords: vec![Keyword {
+                name: "devours".to_string(),
+                data: Some(KeywordData::CardId(CardId {
+                    name: Some("Fly".to_string()),
+                    ..Default::default()
+                })),
+            }],
+            set: "Core".to_string(),
+            other: vec!["promo".to_string()],
+            legality: HashMap::from([("standard".to_string(), "legal".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(built, manual);
+
+        let json = serde_json::to_string(&built).expect("couldn't write card");
+        let read: Card = serde_json::from_str(&json).expect("couldn't read card");
+        assert_eq!(read, built);
+        assert_eq!(Card::builder().build(), Card::default());
+    }
+}
@@ -1,8 +1,10 @@
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
+
+use serde::{Deserialize, Serialize};
 
 use crate::numbers::MaybeImprecise;
 
-use super::{rich_text::RichString, Keyword};
+use super::{rich_text::RichString, Image, Keyword};
 
 /// This trait is used in card generics. It is useful when you want a function to accept `CardId`s and not only `Card`s.
 pub trait Read {
@@ -10,7 +12,7 @@ pub trait Read {
     fn get_num_property(&self, property: &Number) -> Option<MaybeImprecise>;
     /// Return a card's text property, if it has it.
     fn get_text_property(&self, property: &Text) -> Option<String>;
-    /// Return a card's array property, if it has it.
+    /// Return a card's array property, if it has it. A card that has none of something should return an empty array, as `None` means it is unknown.
     fn get_vec_property(&self, property: &Array) -> Option<&[String]>;
     /// Return a card's keywords, if it has them. It may not have them if it is a `CardId`.
     fn get_keywords(&self) -> Option<&[Keyword]>;
@@ -24,10 +26,23 @@ pub trait Read {
     fn get_kins(&self) -> Option<&[String]>;
     /// Return a card's flavor text, if it has one. It may not have one if it is a `CardId`.
     fn get_flavor_text(&self) -> Option<&str>;
+    /// Return a card's images, if it has them. It may not have them if it is a `CardId`, so by default it returns `None`.
+    fn get_images(&self) -> Option<&[Image]> {
+        None
+    }
+    /// Return a card's legality in each format, if it has it. It may not have it if it is a `CardId`, so by default it returns `None`.
+    fn get_legality(&self) -> Option<&HashMap<String, String>> {
+        None
+    }
+    /// Return a card's numeric property as text, or "—" if it doesn't have it, like the stats of commands.
+    fn display_num_property(&self, property: &Number) -> String {
+        self.get_num_property(property)
+            .map_or_else(|| "—".to_string(), |value| value.to_string())
+    }
 }
 
 /// A card's numerical properties
-#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Number {
     Cost,
     Health,
@@ -47,10 +62,12 @@ impl Display for Number {
 }
 
 /// A card's array properties
-#[derive(Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Array {
     Functions,
     Kins,
+    Abilities,
+    Other,
 }
 
 impl Display for Array {
@@ -58,19 +75,22 @@ impl Display for Array {
         match self {
             Self::Functions => write!(f, "Functions"),
             Self::Kins => write!(f, "Kins"),
+            Self::Abilities => write!(f, "Abilities"),
+            Self::Other => write!(f, "Other"),
             // Self::Artists => write!(f, "Artists"),
         }
     }
 }
 
 /// A card's text properties
-#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum Text {
     Id,
     Name,
     Type,
     Description,
     FlavorText,
+    Set,
 }
 
 impl Display for Text {
@@ -81,6 +101,7 @@ impl Display for Text {
             Self::Type => write!(f, "Type"),
             Self::Description => write!(f, "Description"),
             Self::FlavorText => write!(f, "FlavorText"),
+            Self::Set => write!(f, "Set"),
         }
     }
 }
@@ -1,7 +1,7 @@
 //! Bloodless card descriptions can use rich text instead of just Strings. This text may contain links to other cards, or represent a Saga.
 use super::CardId;
 use std::{
-    fmt::Display,
+    fmt::{Display, Write},
     slice::{Iter, IterMut},
     vec::IntoIter,
 };
@@ -13,6 +13,7 @@ use serde::{
 };
 
 /// An element of `RichString`s
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum RichElement {
     String(String),
@@ -71,6 +72,153 @@ impl RichString {
     pub fn push_string(&mut self, str: String) {
         self.elements.push(RichElement::String(str));
     }
+
+    /// Whether there is no text at all: either no elements, or a single empty string.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        match self.elements.as_slice() {
+            [] => true,
+            [RichElement::String(string)] => string.is_empty(),
+            _ => false,
+        }
+    }
+
+    /// Counts the whitespace-separated words in the text, including the displayed text of links and every chapter of sagas.
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.to_string().split_whitespace().count()
+    }
+
+    /// Iterates over every element depth-first, including the elements inside the chapters of `Saga`s. A `Saga` is yielded before its chapters' elements.
+    pub fn iter_all(&self) -> impl Iterator<Item = &RichElement> {
+        AllElements {
+            stack: vec![self.elements.iter()],
+        }
+    }
+
+    /// Returns every element that links to other cards, including the ones inside `Saga`s, in the order `iter_all` visits them.
+    #[must_use]
+    pub fn card_links(&self) -> Vec<&RichElement> {
+        self.iter_all()
+            .filter(|element| {
+                matches!(
+                    element,
+                    RichElement::CardId { .. }
+                        | RichElement::SpecificCard { .. }
+                        | RichElement::CardSearch { .. }
+                )
+            })
+            .collect()
+    }
+
+    /// Renders the text as Markdown. Links to specific cards become `[display](card:id)` and embedded searches become `[display](search:search)`, with spaces and parentheses percent-encoded. Each chapter of a `Saga` becomes an item of a numbered list.
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut markdown = String::new();
+        for element in &self.elements {
+            let at_line_start = markdown.is_empty() || markdown.ends_with('\n');
+            // Writing to a `String` can't fail.
+            let _ = match element {
+                RichElement::String(text) => {
+                    write!(markdown, "{}", escape_markdown(text, at_line_start))
+                }
+                RichElement::CardId { display, .. } => {
+                    write!(markdown, "{}", escape_markdown(display, at_line_start))
+                }
+                RichElement::SpecificCard { display, id } => write!(
+                    markdown,
+                    "[{}](card:{})",
+                    escape_markdown(display, false),
+                    encode_link(id)
+                ),
+                RichElement::CardSearch { display, search } => write!(
+                    markdown,
+                    "[{}](search:{})",
+                    escape_markdown(display, false),
+                    encode_link(search)
+                ),
+                RichElement::Saga(chapters) => {
+                    chapters
+                        .iter()
+                        .enumerate()
+                        .try_for_each(|(number, chapter)| {
+                            writeln!(markdown, "{}. {}", number + 1, chapter.to_markdown())
+                        })
+                }
+                RichElement::LineBreak => writeln!(markdown),
+            };
+        }
+        markdown
+    }
+
+    /// Returns the text without any markup: links are replaced by their displayed text, and every chapter of a `Saga` goes on its own line. This is the same as the `Display` output.
+    #[must_use]
+    pub fn to_plain_text(&self) -> String {
+        self.to_string()
+    }
+
+    /// Returns the id of every `SpecificCard` link, including the ones inside `Saga`s.
+    #[must_use]
+    pub fn referenced_ids(&self) -> Vec<&str> {
+        self.iter_all()
+            .filter_map(|element| match element {
+                RichElement::SpecificCard { id, .. } => Some(id.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Escapes the characters that Markdown would read as formatting. Markers that only mean something at the start of a line, like the `-` of a list item or the `1.` of a numbered one, are escaped there, which includes the start of the text if it's `at_line_start`.
+fn escape_markdown(text: &str, at_line_start: bool) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut line_start = at_line_start;
+    // Whether the line so far is only the digits of what could be a numbered item.
+    let mut item_number = false;
+    for char in text.chars() {
+        if matches!(char, '\\' | '`' | '*' | '_' | '[' | ']' | '#')
+            || line_start && matches!(char, '-' | '+' | '>')
+            || item_number && matches!(char, '.' | ')')
+        {
+            escaped.push('\\');
+        }
+        escaped.push(char);
+        item_number = (line_start || item_number) && char.is_ascii_digit();
+        line_start = char == '\n' || line_start && char == ' ';
+    }
+    escaped
+}
+
+/// Percent-encodes the characters that would end a Markdown link's destination.
+fn encode_link(target: &str) -> String {
+    target
+        .replace('%', "%25")
+        .replace(' ', "%20")
+        .replace('(', "%28")
+        .replace(')', "%29")
+}
+
+/// Iterator for `RichString::iter_all`. Keeps the unvisited elements of every rich string it has descended into.
+struct AllElements<'a> {
+    stack: Vec<Iter<'a, RichElement>>,
+}
+
+impl<'a> Iterator for AllElements<'a> {
+    type Item = &'a RichElement;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some(element) = self.stack.last_mut()?.next() else {
+                self.stack.pop();
+                continue;
+            };
+            if let RichElement::Saga(chapters) = element {
+                self.stack
+                    .extend(chapters.iter().rev().map(|chapter| chapter.elements.iter()));
+            }
+            return Some(element);
+        }
+    }
 }
 
 impl Display for RichElement {
@@ -214,7 +362,7 @@ impl<'de> Deserialize<'de> for RichElement {
                         field => {
                             return Err(serde::de::Error::unknown_field(
                                 field,
-                                &["display", "identity", "id"],
+                                &["display", "identity", "id", "search"],
                             ))
                         }
                     }
@@ -222,9 +370,6 @@ impl<'de> Deserialize<'de> for RichElement {
 
                 match (display, identity, id, search) {
                     (None, _, _, _) => Err(serde::de::Error::missing_field("display")),
-                    (Some(_), Some(_), Some(_), Some(_)) => Err(serde::de::Error::custom(
-                        "expected something with either id or identity",
-                    )),
                     (Some(display), None, None, Some(search)) => {
                         Ok(RichElement::CardSearch { display, search })
                     }
@@ -237,7 +382,9 @@ impl<'de> Deserialize<'de> for RichElement {
                     (Some(_), None, None, None) => Err(serde::de::Error::missing_field(
                         "either id or identity or search",
                     )),
-                    _ => Err(serde::de::Error::custom("what are you DOING")),
+                    _ => Err(serde::de::Error::custom(
+                        "expected only one of id, identity or search",
+                    )),
                 }
             }
         }
@@ -285,7 +432,12 @@ impl Serialize for RichElement {
 
 #[cfg(test)]
 mod test {
-    use crate::{cards::Card, search};
+    use crate::{
+        cards::{Card, CardId},
+        search,
+    };
+
+    use super::{RichElement, RichString};
 
     #[test]
     fn test_serialize() {
@@ -314,4 +466,196 @@ mod test {
             println!("{card}");
         }
     }
+
+    #[test]
+    fn test_iter_all() {
+        let string = |elements: Vec<RichElement>| RichString { elements };
+        let link = |id: &str| RichElement::SpecificCard {
+            display: id.to_string(),
+            id: id.to_string(),
+        };
+        let description = string(vec![
+            RichElement::String("Saga:".to_string()),
+            RichElement::Saga(vec![
+                string(vec![
+                    RichElement::String("Summon ".to_string()),
+                    link("ant"),
+                ]),
+                string(vec![RichElement::Saga(vec![string(vec![link("bee")])])]),
+                string(vec![RichElement::CardId {
+                    display: "a wasp".to_string(),
+                    identity: CardId::builder().name("wasp").build(),
+                }]),
+            ]),
+            RichElement::LineBreak,
+        ]);
+
+        let visited: Vec<String> = description
+            .iter_all()
+            .map(|element| match element {
+                RichElement::Saga(chapters) => format!("saga of {}", chapters.len()),
+                RichElement::LineBreak => "line break".to_string(),
+                element => element.to_string(),
+            })
+            .collect();
+        assert_eq!(
+            visited,
+            [
+                "Saga:",
+                "saga of 3",
+                "Summon ",
+                "ant",
+                "saga of 1",
+                "bee",
+                "a wasp",
+                "line break"
+            ]
+        );
+        assert_eq!(RichString::default().iter_all().count(), 0);
+    }
+
+    #[test]
+    fn test_rich_element_round_trip() {
+        let elements = [
+            RichElement::CardId {
+                display: "an ant".to_string(),
+                identity: CardId::builder().name("ant").kin("insect").build(),
+            },
+            RichElement::SpecificCard {
+                display: "the Green Queen".to_string(),
+                id: "green-queen-001".to_string(),
+            },
+            RichElement::CardSearch {
+                display: "cheap insects".to_string(),
+                search: "k:insect c<2".to_string(),
+            },
+        ];
+
+        for element in elements {
+            let json = serde_json::to_string(&element).expect("couldn't serialize element");
+            let read: RichElement =
+                serde_json::from_str(&json).expect("couldn't deserialize element");
+            assert_eq!(read, element, "{json}");
+        }
+    }
+
+    #[test]
+    fn test_rich_element_errors() {
+        let error = |json: &str| {
+            serde_json::from_str::<RichElement>(json)
+                .expect_err("element shouldn't deserialize")
+                .to_string()
+        };
+
+        assert!(
+            error(r#"{"display": "ant", "id": "ant-001", "identity": {"name": "ant"}}"#)
+                .contains("expected only one of id, identity or search")
+        );
+        assert!(error(r#"{"display": "ant"}"#).contains("either id or identity or search"));
+        assert!(error(r#"{"id": "ant-001"}"#).contains("missing field `display`"));
+        assert!(error(r#"{"display": "ants", "serch": "k:ant"}"#)
+            .contains("`display`, `identity`, `id`, `search`"));
+    }
+
+    #[test]
+    fn test_card_links() {
+        let string = |elements: Vec<RichElement>| RichString { elements };
+        let link = |id: &str| RichElement::SpecificCard {
+            display: id.to_string(),
+            id: id.to_string(),
+        };
+        let wasp = RichElement::CardId {
+            display: "a wasp".to_string(),
+            identity: CardId::builder().name("wasp").build(),
+        };
+        let search = RichElement::CardSearch {
+            display: "an insect".to_string(),
+            search: "k:insect".to_string(),
+        };
+        let description = string(vec![
+            RichElement::String("Summon ".to_string()),
+            link("ant"),
+            RichElement::Saga(vec![
+                string(vec![RichElement::String("Then ".to_string()), wasp.clone()]),
+                string(vec![RichElement::Saga(vec![string(vec![
+                    link("bee"),
+                    search.clone(),
+                ])])]),
+            ]),
+        ]);
+
+        assert_eq!(
+            description.card_links(),
+            [&link("ant"), &wasp, &link("bee"), &search]
+        );
+        assert_eq!(description.referenced_ids(), ["ant", "bee"]);
+        assert!(string(vec![RichElement::String("Nothing".to_string())])
+            .card_links()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_markdown_and_plain_text() {
+        let card: Card = serde_json::from_str(
+            &std::fs::read_to_string("tests/rich.json").expect("Couldn't load rich.json"),
+        )
+        .expect("Couldn't convert rich.json to a card");
+        assert_eq!(
+            card.description.to_markdown(),
+            "1. Pay 1 blood to deal 2 damage to any creature.\n\
+             2. Shuffle a grand design vestige into your deck.\n\
+             3. Add 2 points to the health pool.\n"
+        );
+        assert_eq!(
+            card.description.to_plain_text(),
+            "Pay 1 blood to deal 2 damage to any creature.\n\
+             Shuffle a grand design vestige into your deck.\n\
+             Add 2 points to the health pool.\n"
+        );
+
+        let description = RichString {
+            elements: vec![
+                RichElement::String("Summon *one* ".to_string()),
+                RichElement::SpecificCard {
+                    display: "Ant".to_string(),
+                    id: "ant".to_string(),
+                },
+                RichElement::LineBreak,
+                RichElement::String("or ".to_string()),
+                RichElement::CardSearch {
+                    display: "an insect".to_string(),
+                    search: "k:insect (c:1)".to_string(),
+                },
+                RichElement::String(" or ".to_string()),
+                RichElement::CardId {
+                    display: "a wasp".to_string(),
+                    identity: CardId::builder().name("wasp").build(),
+                },
+            ],
+        };
+        assert_eq!(
+            description.to_markdown(),
+            "Summon \\*one\\* [Ant](card:ant)\nor [an insect](search:k:insect%20%28c:1%29) or a wasp"
+        );
+        assert_eq!(
+            description.to_plain_text(),
+            "Summon *one* Ant\nor an insect or a wasp"
+        );
+
+        let description = RichString {
+            elements: vec![
+                RichElement::String("1. Pay 2.5 blood - or not.\n- Draw".to_string()),
+                RichElement::LineBreak,
+                RichElement::String("  + Discard\n> Quote\n12) Twelve".to_string()),
+                RichElement::SpecificCard {
+                    display: "- Ant".to_string(),
+                    id: "ant".to_string(),
+                },
+            ],
+        };
+        assert_eq!(
+            description.to_markdown(),
+            "1\\. Pay 2.5 blood - or not.\n\\- Draw\n  \\+ Discard\n\\> Quote\n12\\) Twelve[- Ant](card:ant)"
+        );
+    }
 }
@@ -0,0 +1,346 @@
+//! A pool of cards kept together with what's needed to search it quickly.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    cards::{properties::Array, Card},
+    clean_ascii,
+    search::{query_parser::query_parser, search_among, Errors, Query, QueryRestriction},
+};
+
+/// Owns a pool of cards, along with their searchable text and indexes of their kins and keywords.
+///
+/// The indexes are kept up to date by `add` and `remove`, so the cards can only be changed through those.
+#[derive(Debug, Clone, Default)]
+pub struct CardDatabase {
+    /// The cards, in the order they were added. Removing a card leaves its slot empty, so the positions the indexes point to never move.
+    slots: Vec<Option<Card>>,
+    /// The text `fuzzy` looks at for each card, cleaned with `clean_ascii`, in the same order as `slots`. Empty for empty slots.
+    searchable: Vec<String>,
+    /// Positions in `slots` of the cards with each kin, keyed by the kin cleaned with `clean_ascii`.
+    kins: HashMap<String, Vec<usize>>,
+    /// Positions in `slots` of the cards with each keyword, keyed by the keyword cleaned with `clean_ascii`.
+    keywords: HashMap<String, Vec<usize>>,
+    /// How many slots have a card in them.
+    len: usize,
+}
+
+impl From<Vec<Card>> for CardDatabase {
+    fn from(cards: Vec<Card>) -> Self {
+        let mut database = Self::default();
+        for card in cards {
+            database.add(card);
+        }
+        database
+    }
+}
+
+impl CardDatabase {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the cards in the order they were added.
+    pub fn cards(&self) -> impl Iterator<Item = &Card> + Clone {
+        self.slots.iter().flatten()
+    }
+
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the card with this `id`, if there is one.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&Card> {
+        self.cards().find(|card| card.id == id)
+    }
+
+    /// Adds a card to the database and indexes it.
+    pub fn add(&mut self, card: Card) {
+        let index = self.slots.len();
+        self.searchable.push(searchable_text(&card));
+        for kin in &card.kins {
+            self.kins.entry(clean_ascii(kin)).or_default().push(index);
+        }
+        for keyword in &card.keywords {
+            self.keywords
+                .entry(clean_ascii(&keyword.name))
+                .or_default()
+                .push(index);
+        }
+        self.slots.push(Some(card));
+        self.len += 1;
+    }
+
+    /// Removes the card with this `id` and returns it, if there is one.
+    ///
+    /// Only the index entries of the removed card are touched.
+    pub fn remove(&mut self, id: &str) -> Option<Card> {
+        let index = self
+            .slots
+            .iter()
+            .position(|slot| slot.as_ref().is_some_and(|card| card.id == id))?;
+        let card = self.slots[index].take()?;
+        self.searchable[index].clear();
+        for kin in &card.kins {
+            unindex(&mut self.kins, &clean_ascii(kin), index);
+        }
+        for keyword in &card.keywords {
+            unindex(&mut self.keywords, &clean_ascii(&keyword.name), index);
+        }
+        self.len -= 1;
+        Some(card)
+    }
+
+    /// Returns the cards that match the `query`, sorted as the query asks.
+    ///
+    /// The indexes narrow down which cards need to be checked, but the results are the same as `search` over `cards`.
+    #[must_use]
+    pub fn search(&self, query: &Query) -> Vec<&Card> {
+        search_among(query, self.candidates(query), &self.cards())
+    }
+
+    /// Parses the `query` and returns the cards that match it.
+    ///
+    /// # Errors
+    /// When the query can't be parsed.
+    pub fn search_str(&self, query: &str) -> Result<Vec<&Card>, Errors> {
+        Ok(self.search(&query_parser(query)?))
+    }
+
+    /// Returns the cards with a kin that is exactly `kin`, ignoring casing and diaereses.
+    #[must_use]
+    pub fn with_kin(&self, kin: &str) -> Vec<&Card> {
+        self.in_slots(
+            self.kins
+                .get(&clean_ascii(kin))
+                .into_iter()
+                .flatten()
+                .copied(),
+        )
+    }
+
+    /// Returns the cards with a keyword that is exactly `keyword`, ignoring casing and diaereses.
+    #[must_use]
+    pub fn with_keyword(&self, keyword: &str) -> Vec<&Card> {
+        self.in_slots(
+            self.keywords
+                .get(&clean_ascii(keyword))
+                .into_iter()
+                .flatten()
+                .copied(),
+        )
+    }
+
+    /// Returns the cards that have `text` written on them, like `fuzzy` does, without cleaning each card's text again.
+    #[must_use]
+    pub fn with_text(&self, text: &str) -> Vec<&Card> {
+        self.in_slots(self.text_slots(text))
+    }
+
+    /// Uses the indexes to find the cards that can match the `query`. Only restrictions that every match has to meet are looked at, and they only rule out cards that can't meet them, so no card that matches is left out.
+    fn candidates(&self, query: &Query) -> Vec<&Card> {
+        let mut candidates: Option<HashSet<usize>> = None;
+        for restriction in &query.restrictions {
+            let slots: HashSet<usize> = match restriction {
+                QueryRestriction::Fuzzy(text) => self.text_slots(text).collect(),
+                QueryRestriction::HasKw(keyword) => containing(&self.keywords, keyword),
+                QueryRestriction::Has(Array::Kins, kin) => containing(&self.kins, kin),
+                _ => continue,
+            };
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&slots).copied().collect(),
+                None => slots,
+            });
+        }
+        candidates.map_or_else(
+            || self.cards().collect(),
+            |candidates| {
+                let mut candidates: Vec<usize> = candidates.into_iter().collect();
+                candidates.sort_unstable();
+                self.in_slots(candidates)
+            },
+        )
+    }
+
+    /// Returns the positions of the cards whose searchable text contains `text`.
+    fn text_slots(&self, text: &str) -> impl Iterator<Item = usize> + '_ {
+        let text = clean_ascii(text);
+        self.searchable
+            .iter()
+            .enumerate()
+            .filter(move |(_, searchable)| searchable.contains(&text))
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the cards in these slots, skipping empty ones.
+    fn in_slots(&self, slots: impl IntoIterator<Item = usize>) -> Vec<&Card> {
+        slots
+            .into_iter()
+            .filter_map(|index| self.slots[index].as_ref())
+            .collect()
+    }
+}
+
+/// Returns the positions the `index` has for every key that contains `text`, ignoring casing and diaereses, the same way `Has` and `HasKw` compare them.
+fn containing(index: &HashMap<String, Vec<usize>>, text: &str) -> HashSet<usize> {
+    let text = clean_ascii(text);
+    index
+        .iter()
+        .filter(|(key, _)| key.contains(&text))
+        .flat_map(|(_, slots)| slots.iter().copied())
+        .collect()
+}
+
+/// Removes the slot at `index` from the positions the `index_map` has for `key`, dropping the key once no card has it.
+fn unindex(index_map: &mut HashMap<String, Vec<usize>>, key: &str, index: usize) {
+    if let Some(slots) = index_map.get_mut(key) {
+        slots.retain(|&slot| slot != index);
+        if slots.is_empty() {
+            index_map.remove(key);
+        }
+    }
+}
+
+/// Joins the card's fields that `fuzzy` looks at into one cleaned string. Fields are separated by line breaks so that text can't match across two of them.
+fn searchable_text(card: &Card) -> String {
+    let mut fields = vec![
+        card.description.to_string(),
+        card.name.clone(),
+        card.r#type.clone(),
+    ];
+    fields.extend(card.kins.iter().cloned());
+    fields.extend(card.keywords.iter().map(|keyword| keyword.name.clone()));
+    fields.push(card.flavor_text.clone());
+    fields.extend(card.functions.iter().cloned());
+    clean_ascii(&fields.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cards::{Card, Keyword},
+        search::{query_parser::query_parser, search},
+    };
+
+    use super::CardDatabase;
+
+    fn card(id: &str, name: &str, kins: &[&str], keywords: &[&str]) -> Card {
+        Card {
+            id: id.to_string(),
+            name: name.to_string(),
+            r#type: "creature".to_string(),
+            kins: kins.iter().map(ToString::to_string).collect(),
+            keywords: keywords
+                .iter()
+                .map(|name| Keyword {
+                    name: (*name).to_string(),
+                    data: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn names(cards: &[&Card]) -> Vec<String> {
+        cards.iter().map(|card| card.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_card_database() {
+        let mut database = CardDatabase::from(vec![
+            card("ant", "Ant", &["ant", "insect"], &[]),
+            card("bee", "Bee", &["bee", "insect"], &["flying"]),
+            card("toad", "Toad", &["amphibian"], &["devours"]),
+        ]);
+        assert_eq!(database.len(), 3);
+
+        assert_eq!(
+            names(&database.search_str("k:insect").expect("couldn't search")),
+            ["Ant", "Bee"]
+        );
+        assert!(database.search_str("k:(").is_err());
+        assert_eq!(names(&database.with_kin("Insect")), ["Ant", "Bee"]);
+        assert_eq!(names(&database.with_keyword("flying")), ["Bee"]);
+        assert_eq!(names(&database.with_text("amphib")), ["Toad"]);
+
+        database.add(card("moth", "Moth", &["moth", "insect"], &["flying"]));
+        assert_eq!(names(&database.with_keyword("flying")), ["Bee", "Moth"]);
+
+        let removed = database
+            .remove("bee")
+            .expect("bee should be in the database");
+        assert_eq!(removed.name, "Bee");
+        assert!(database.remove("bee").is_none());
+        assert!(database.get("bee").is_none());
+        assert_eq!(names(&database.with_kin("insect")), ["Ant", "Moth"]);
+        assert_eq!(names(&database.with_keyword("flying")), ["Moth"]);
+        assert_eq!(
+            names(&database.search_str("k:insect").expect("couldn't search")),
+            ["Ant", "Moth"]
+        );
+    }
+
+    #[test]
+    fn test_database_search_matches_search() {
+        let mut cards = vec![
+            card("ant", "Ant", &["ant", "insect"], &[]),
+            card("bee", "Bee", &["bee", "insect"], &["flying"]),
+            card("toad", "Toad", &["amphibian"], &["devours"]),
+            card("moth", "Moth", &["moth", "insect"], &["flying"]),
+            card("ant-2", "Ant", &["ant", "insect"], &["flying"]),
+        ];
+        cards[2].keywords[0].data = serde_json::from_str(r#"{"type": "CardId", "kins": "insect"}"#)
+            .expect("couldn't read keyword data");
+        let mut database = CardDatabase::from(cards.clone());
+        database.remove("bee");
+        cards.remove(1);
+
+        for query in [
+            "k:insect",
+            "k:sect kw:fly",
+            "an",
+            "t:creature -kw:flying",
+            "kw:flying OR k:amphibian",
+            "devouredby:(n:toad) kw:flying",
+            "is:reprint k:ant",
+            "k:insect so:name",
+            "k:bee",
+        ] {
+            let query = query_parser(query).expect("couldn't parse query");
+            assert_eq!(
+                names(&database.search(&query)),
+                names(&search(&query, cards.iter())),
+                "{query}"
+            );
+        }
+        assert_eq!(
+            names(
+                &database
+                    .search_str("devouredby:(n:toad) kw:flying")
+                    .expect("couldn't search")
+            ),
+            ["Ant", "Moth"]
+        );
+        assert_eq!(
+            names(
+                &database
+                    .search_str("is:reprint k:ant")
+                    .expect("couldn't search")
+            ),
+            ["Ant", "Ant"]
+        );
+        assert!(database
+            .search_str("k:bee")
+            .expect("couldn't search")
+            .is_empty());
+        assert_eq!(database.cards().count(), database.len());
+    }
+}
@@ -7,11 +7,15 @@
 //!
 //! This library contains the search functions used by Hemolymph.
 
+pub mod analysis;
 pub mod cards;
+pub mod database;
 pub mod numbers;
 pub mod search;
 
 /// Only handles lowercase because it'll be applied after `to_lowercase`
+///
+/// Quotes, apostrophes, periods and commas are removed too, so text searches ignore them even if a query escapes them with `\"`. Use a regex to match them exactly.
 fn clean_ascii(string: &str) -> String {
     let string = string.to_lowercase();
     clean_ascii_keep_case(&string)
@@ -2,6 +2,7 @@ pub mod compare;
 pub mod imprecise_ord;
 use std::{
     cmp::Ordering,
+    collections::HashMap,
     fmt::{Debug, Display},
     num::TryFromIntError,
 };
@@ -36,6 +37,43 @@ impl Display for MaybeImprecise {
 }
 
 impl MaybeImprecise {
+    /// The number this value is assumed to be when a single number is needed. Precise values use `MaybeVar::assume`, and imprecise ones use the smallest number they admit.
+    #[must_use]
+    pub const fn assume(&self) -> usize {
+        match self {
+            Self::Precise(x) => x.assume(),
+            Self::Imprecise(x) => x.bounds().0,
+        }
+    }
+
+    /// A single number to rank this value by: precise values use their assumed value, and imprecise ones use the middle of the numbers they admit. Imprecise values with no upper bound, like `>3`, are ranked one above their lower bound.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub const fn rank_value(&self) -> f64 {
+        match self {
+            Self::Precise(x) => x.assume() as f64,
+            Self::Imprecise(x) => match x.bounds() {
+                (lower, Some(upper)) => f64::midpoint(lower as f64, upper as f64),
+                (lower, None) => lower as f64 + 1.0,
+            },
+        }
+    }
+
+    /// Replaces a variable with the number it is bound to in `bindings`. Unbound variables and imprecise values are left as they are.
+    #[must_use]
+    pub fn substitute(&self, bindings: &HashMap<char, usize>) -> Self {
+        match self {
+            Self::Precise(MaybeVar::Var(var)) => bindings
+                .get(var)
+                .map_or_else(|| self.clone(), |x| Self::Precise(MaybeVar::Const(*x))),
+            Self::Precise(MaybeVar::VarPlus(var, offset)) => bindings.get(var).map_or_else(
+                || self.clone(),
+                |x| Self::Precise(MaybeVar::Const(x + offset)),
+            ),
+            _ => self.clone(),
+        }
+    }
+
     #[must_use]
     pub const fn as_comparison(&self) -> Comparison {
         match self {
@@ -50,6 +88,8 @@ impl MaybeImprecise {
 pub enum MaybeVar {
     Const(usize),
     Var(char),
+    /// A variable plus a constant offset, like `X+1`.
+    VarPlus(char, usize),
 }
 
 impl Display for MaybeVar {
@@ -57,6 +97,7 @@ impl Display for MaybeVar {
         match self {
             Self::Const(x) => write!(f, "{x}"),
             Self::Var(x) => write!(f, "{x}"),
+            Self::VarPlus(x, offset) => write!(f, "{x}+{offset}"),
         }
     }
 }
@@ -68,11 +109,11 @@ impl Default for MaybeVar {
 }
 
 impl MaybeVar {
-    /// If a number is a variable, it will usually be assumed to be zero. This might change in the future.
+    /// If a number is a variable, it will usually be assumed to be zero, so `X+1` is assumed to be 1. This might change in the future.
     #[must_use]
     pub const fn assume(&self) -> usize {
         match self {
-            Self::Const(x) => *x,
+            Self::Const(x) | Self::VarPlus(_, x) => *x,
             Self::Var(_) => 0,
         }
     }
@@ -86,9 +127,13 @@ pub trait Compare {
     fn lt_eq(&self, comparison: usize) -> Ternary;
     fn eq(&self, comparison: usize) -> Ternary;
     fn ne(&self, comparison: usize) -> Ternary;
+    /// Whether the value can be between `low` and `high`, both included.
+    fn between(&self, low: usize, high: usize) -> Ternary;
 }
 
-/// A version of ordering that works over ranges and does not necessitate a notion of equality, which cannot be defined for Bloodless number ranges
+/// A version of ordering that works over ranges and does not necessitate a notion of equality, which cannot be defined for Bloodless number ranges.
+///
+/// Ranges are ordered by the smallest number they admit first, and by the largest one second, with ranges that have no upper bound going last. This makes the ordering total, but two different ranges may still be considered `Equal`, like `!= 3` and `>= 0`.
 pub trait ImpreciseOrd<Other> {
     fn imprecise_cmp(&self, other: &Other) -> Ordering;
 }
@@ -102,6 +147,11 @@ pub enum Comparison {
     Equal(usize),
     LowerThan(usize),
     NotEqual(usize),
+    /// Every number from `low` to `high`, both included. `low` shouldn't be greater than `high`.
+    Range {
+        low: usize,
+        high: usize,
+    },
 }
 
 impl Display for Comparison {
@@ -113,17 +163,35 @@ impl Display for Comparison {
             Self::Equal(number) => write!(f, "= {number}"),
             Self::LowerThan(number) => write!(f, "< {number}"),
             Self::NotEqual(number) => write!(f, "!= {number}"),
+            Self::Range { low, high } => write!(f, "{low}..{high}"),
         }
     }
 }
 
 impl Comparison {
+    /// Returns the smallest number the comparison admits and the largest one, or `None` if it has no upper bound.
+    /// `LowerThan(0)` admits no numbers at all, so it is given the same bounds as `Equal(0)`.
+    #[must_use]
+    pub const fn bounds(&self) -> (usize, Option<usize>) {
+        match *self {
+            Self::Equal(x) => (x, Some(x)),
+            Self::GreaterThan(x) => (x.saturating_add(1), None),
+            Self::GreaterThanOrEqual(x) => (x, None),
+            Self::LowerThan(x) => (0, Some(x.saturating_sub(1))),
+            Self::LowerThanOrEqual(x) => (0, Some(x)),
+            Self::NotEqual(0) => (1, None),
+            Self::NotEqual(_) => (0, None),
+            Self::Range { low, high } => (low, Some(high)),
+        }
+    }
+
     pub fn compare<T: Compare + Debug>(&self, a: &T) -> Ternary {
         match self {
             Self::GreaterThan(x) => a.gt(*x),
             Self::Equal(x) => a.eq(*x),
             Self::LowerThan(x) => a.lt(*x),
             Self::NotEqual(x) => a.ne(*x),
+            Self::Range { low, high } => a.between(*low, *high),
             Self::GreaterThanOrEqual(x) => a.gt_eq(*x),
             Self::LowerThanOrEqual(x) => a.lt_eq(*x),
         }
@@ -137,7 +205,7 @@ impl Serialize for MaybeVar {
     {
         match self {
             Self::Const(x) => serializer.serialize_u64((*x).try_into().unwrap()),
-            Self::Var(x) => serializer.serialize_str(&x.to_string()),
+            Self::Var(_) | Self::VarPlus(..) => serializer.serialize_str(&self.to_string()),
         }
     }
 }
@@ -156,6 +224,9 @@ impl Serialize for MaybeImprecise {
                 Comparison::LowerThan(x) => serializer.serialize_str(&format!("<{x}")),
                 Comparison::LowerThanOrEqual(x) => serializer.serialize_str(&format!("<={x}")),
                 Comparison::NotEqual(x) => serializer.serialize_str(&format!("!={x}")),
+                Comparison::Range { low, high } => {
+                    serializer.serialize_str(&format!("{low}..{high}"))
+                }
             },
         }
     }
@@ -176,7 +247,7 @@ impl<'de> Deserialize<'de> for MaybeImprecise {
         D: Deserializer<'de>,
     {
         struct MiVisitor;
-        impl<'de> Visitor<'de> for MiVisitor {
+        impl Visitor<'_> for MiVisitor {
             type Value = MaybeImprecise;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -215,7 +286,7 @@ impl<'de> Deserialize<'de> for MaybeImprecise {
 
 fn deserialize_maybe_var<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MaybeVar, D::Error> {
     struct MvVisitor;
-    impl<'de> Visitor<'de> for MvVisitor {
+    impl Visitor<'_> for MvVisitor {
         type Value = MaybeVar;
 
         fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -226,12 +297,11 @@ fn deserialize_maybe_var<'de, D: Deserializer<'de>>(deserializer: D) -> Result<M
         where
             E: Error,
         {
-            match v.chars().next() {
-                Some(char) if char.is_alphabetic() => Ok(MaybeVar::Var(char)),
-                _ => Err(Error::custom(
-                    "numbers can only be single letters or integers",
-                )),
-            }
+            str_as_maybe_var(v).ok_or_else(|| {
+                Error::custom(
+                    "numbers can only be single letters, a letter plus an integer, or integers",
+                )
+            })
         }
 
         fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
@@ -256,9 +326,87 @@ fn u64_as_maybe_var(v: u64) -> Result<MaybeVar, TryFromIntError> {
     }
 }
 
+/// Reads a variable like `X`, or a variable plus an offset like `X+1`.
 fn str_as_maybe_var(v: &str) -> Option<MaybeVar> {
-    v.chars()
-        .next()
-        .filter(|x| x.is_alphabetic())
-        .map(MaybeVar::Var)
+    let mut chars = v.chars();
+    let var = chars.next().filter(|x| x.is_alphabetic())?;
+    chars
+        .as_str()
+        .trim_start()
+        .strip_prefix('+')
+        .map_or(Some(MaybeVar::Var(var)), |offset| {
+            offset
+                .trim()
+                .parse()
+                .ok()
+                .map(|offset| MaybeVar::VarPlus(var, offset))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cmp::Ordering, collections::HashMap};
+
+    use crate::search::Ternary;
+
+    use super::{Comparison, ImpreciseOrd, MaybeImprecise, MaybeVar};
+
+    #[test]
+    fn test_substitute() {
+        let bindings = HashMap::from([('X', 3)]);
+
+        let bound = MaybeImprecise::Precise(MaybeVar::Var('X'));
+        assert_eq!(
+            bound.substitute(&bindings),
+            MaybeImprecise::Precise(MaybeVar::Const(3))
+        );
+
+        let unbound = MaybeImprecise::Precise(MaybeVar::Var('Y'));
+        assert_eq!(unbound.substitute(&bindings), unbound);
+
+        let imprecise = MaybeImprecise::Imprecise(Comparison::GreaterThan(2));
+        assert_eq!(imprecise.substitute(&bindings), imprecise);
+
+        let offset = MaybeImprecise::Precise(MaybeVar::VarPlus('X', 1));
+        assert_eq!(
+            offset.substitute(&bindings),
+            MaybeImprecise::Precise(MaybeVar::Const(4))
+        );
+    }
+
+    #[test]
+    fn test_var_plus() {
+        let number: MaybeImprecise = serde_json::from_str(r#""X+1""#).expect("couldn't parse X+1");
+        assert_eq!(number, MaybeImprecise::Precise(MaybeVar::VarPlus('X', 1)));
+        assert_eq!(number.to_string(), "X+1");
+        assert_eq!(
+            serde_json::to_string(&number).expect("couldn't serialize X+1"),
+            r#""X+1""#
+        );
+
+        let var: MaybeVar = serde_json::from_str(r#""Y+2""#).expect("couldn't parse Y+2");
+        assert_eq!(var, MaybeVar::VarPlus('Y', 2));
+        assert_eq!(
+            serde_json::from_str::<MaybeVar>(r#""X""#).expect("couldn't parse X"),
+            MaybeVar::Var('X')
+        );
+        assert!(serde_json::from_str::<MaybeVar>(r#""X+Y""#).is_err());
+    }
+
+    #[test]
+    fn test_compare_var_plus() {
+        let number = MaybeImprecise::Precise(MaybeVar::VarPlus('X', 1));
+        assert_eq!(number.assume(), 1);
+        assert_eq!(Comparison::Equal(1).compare(&number), Ternary::True);
+        assert_eq!(Comparison::GreaterThan(0).compare(&number), Ternary::True);
+        assert_eq!(Comparison::LowerThan(1).compare(&number), Ternary::False);
+
+        let var = MaybeImprecise::Precise(MaybeVar::Var('X'));
+        assert_eq!(number.imprecise_cmp(&var), Ordering::Greater);
+        assert_eq!(
+            number.imprecise_cmp(&MaybeImprecise::Precise(MaybeVar::Const(1))),
+            Ordering::Equal
+        );
+        assert_ne!(number, MaybeImprecise::Precise(MaybeVar::VarPlus('X', 2)));
+    }
 }
@@ -26,6 +26,11 @@ impl<T: Compare> Compare for Option<T> {
     fn ne(&self, comparison: usize) -> Ternary {
         self.as_ref().map_or(Ternary::Void, |x| x.ne(comparison))
     }
+
+    fn between(&self, low: usize, high: usize) -> Ternary {
+        self.as_ref()
+            .map_or(Ternary::Void, |x| x.between(low, high))
+    }
 }
 
 impl Compare for MaybeImprecise {
@@ -36,8 +41,10 @@ impl Compare for MaybeImprecise {
                 Comparison::GreaterThan(_)
                 | Comparison::GreaterThanOrEqual(_)
                 | Comparison::NotEqual(_) => Ternary::True,
-                Comparison::LowerThan(x) => (*x > comparison + 1).into(),
-                Comparison::LowerThanOrEqual(x) | Comparison::Equal(x) => (*x > comparison).into(),
+                Comparison::LowerThan(x) => (*x > comparison.saturating_add(1)).into(),
+                Comparison::LowerThanOrEqual(x)
+                | Comparison::Equal(x)
+                | Comparison::Range { high: x, .. } => (*x > comparison).into(),
             },
         }
     }
@@ -46,13 +53,13 @@ impl Compare for MaybeImprecise {
         match self {
             Self::Precise(x) => (x.assume() >= comparison).into(),
             Self::Imprecise(x) => match x {
-                Comparison::Equal(x) => (*x >= comparison).into(),
+                Comparison::Equal(x)
+                | Comparison::LowerThanOrEqual(x)
+                | Comparison::Range { high: x, .. } => (*x >= comparison).into(),
                 Comparison::GreaterThan(_)
                 | Comparison::GreaterThanOrEqual(_)
                 | Comparison::NotEqual(_) => Ternary::True,
-                Comparison::LowerThan(x) | Comparison::LowerThanOrEqual(x) => {
-                    (*x > comparison).into()
-                }
+                Comparison::LowerThan(x) => (*x > comparison).into(),
             },
         }
     }
@@ -61,13 +68,14 @@ impl Compare for MaybeImprecise {
         match self {
             Self::Precise(x) => (x.assume() < comparison).into(),
             Self::Imprecise(x) => match x {
-                Comparison::GreaterThan(x) => (*x < comparison - 1).into(),
-                Comparison::GreaterThanOrEqual(x) | Comparison::Equal(x) => {
-                    (*x < comparison).into()
-                }
-                Comparison::LowerThan(_)
-                | Comparison::LowerThanOrEqual(_)
-                | Comparison::NotEqual(_) => Ternary::True,
+                Comparison::GreaterThan(x) => (x.saturating_add(1) < comparison).into(),
+                Comparison::GreaterThanOrEqual(x)
+                | Comparison::Equal(x)
+                | Comparison::Range { low: x, .. } => (*x < comparison).into(),
+                // Nothing is lower than 0, so `<0` admits no numbers at all.
+                Comparison::LowerThan(x) => (*x > 0 && comparison > 0).into(),
+                Comparison::LowerThanOrEqual(_) => (comparison > 0).into(),
+                Comparison::NotEqual(x) => (comparison > 1 || (comparison == 1 && *x != 0)).into(),
             },
         }
     }
@@ -76,13 +84,13 @@ impl Compare for MaybeImprecise {
         match self {
             Self::Precise(x) => (x.assume() <= comparison).into(),
             Self::Imprecise(x) => match x {
-                Comparison::Equal(x) => (*x <= comparison).into(),
-                Comparison::GreaterThan(x) | Comparison::GreaterThanOrEqual(x) => {
-                    (*x < comparison).into()
-                }
-                Comparison::LowerThan(_)
-                | Comparison::LowerThanOrEqual(_)
-                | Comparison::NotEqual(_) => Ternary::True,
+                Comparison::Equal(x)
+                | Comparison::GreaterThanOrEqual(x)
+                | Comparison::Range { low: x, .. } => (*x <= comparison).into(),
+                Comparison::GreaterThan(x) => (*x < comparison).into(),
+                Comparison::LowerThan(x) => (*x > 0).into(),
+                Comparison::LowerThanOrEqual(_) => Ternary::True,
+                Comparison::NotEqual(x) => (comparison > 0 || *x != 0).into(),
             },
         }
     }
@@ -97,6 +105,9 @@ impl Compare for MaybeImprecise {
                 Comparison::LowerThan(x) => (comparison < *x).into(),
                 Comparison::LowerThanOrEqual(x) => (comparison <= *x).into(),
                 Comparison::NotEqual(x) => (comparison != *x).into(),
+                Comparison::Range { low, high } => {
+                    (*low <= comparison && comparison <= *high).into()
+                }
             },
         }
     }
@@ -108,12 +119,35 @@ impl Compare for MaybeImprecise {
                 Comparison::Equal(x) => (comparison != *x).into(),
                 Comparison::GreaterThan(_)
                 | Comparison::GreaterThanOrEqual(_)
-                | Comparison::LowerThan(_)
-                | Comparison::LowerThanOrEqual(_)
                 | Comparison::NotEqual(_) => Ternary::True,
+                Comparison::LowerThan(x) => (*x > 1 || (*x == 1 && comparison != 0)).into(),
+                Comparison::LowerThanOrEqual(x) => (*x > 0 || comparison != 0).into(),
+                Comparison::Range { low, high } => {
+                    (low < high || (low == high && *low != comparison)).into()
+                }
             },
         }
     }
+
+    fn between(&self, low: usize, high: usize) -> Ternary {
+        if low > high {
+            return Ternary::False;
+        }
+        match self {
+            Self::Precise(x) => (low..=high).contains(&x.assume()).into(),
+            // `!= x` admits every number in the range unless the range is just `x`.
+            Self::Imprecise(Comparison::NotEqual(x)) => (low < high || low != *x).into(),
+            // Nothing is lower than 0, so `<0` admits no numbers at all.
+            Self::Imprecise(Comparison::LowerThan(0)) => Ternary::False,
+            Self::Imprecise(x) => {
+                let (min, max) = x.bounds();
+                (min <= max.unwrap_or(usize::MAX)
+                    && min <= high
+                    && max.is_none_or(|max| max >= low))
+                .into()
+            }
+        }
+    }
 }
 
 impl Compare for usize {
@@ -140,4 +174,99 @@ impl Compare for usize {
     fn ne(&self, comparison: usize) -> Ternary {
         (*self != comparison).into()
     }
+
+    fn between(&self, low: usize, high: usize) -> Ternary {
+        (low..=high).contains(self).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        numbers::{Compare, Comparison, MaybeImprecise, MaybeVar},
+        search::Ternary,
+    };
+
+    /// Every number up to this one is checked, which is enough for operands and comparison values below 20.
+    const LIMIT: usize = 64;
+
+    fn admits(value: &MaybeImprecise, number: usize) -> bool {
+        match value {
+            MaybeImprecise::Precise(x) => x.assume() == number,
+            MaybeImprecise::Imprecise(x) => match *x {
+                Comparison::Equal(x) => number == x,
+                Comparison::GreaterThan(x) => number > x,
+                Comparison::GreaterThanOrEqual(x) => number >= x,
+                Comparison::LowerThan(x) => number < x,
+                Comparison::LowerThanOrEqual(x) => number <= x,
+                Comparison::NotEqual(x) => number != x,
+                Comparison::Range { low, high } => (low..=high).contains(&number),
+            },
+        }
+    }
+
+    /// Whether any number admitted by `value` satisfies `predicate`.
+    fn oracle(value: &MaybeImprecise, predicate: impl Fn(usize) -> bool) -> Ternary {
+        (0..LIMIT)
+            .any(|number| admits(value, number) && predicate(number))
+            .into()
+    }
+
+    fn values() -> Vec<MaybeImprecise> {
+        let mut values = vec![MaybeImprecise::Precise(MaybeVar::Var('X'))];
+        for x in 0..6 {
+            values.push(MaybeImprecise::Precise(MaybeVar::Const(x)));
+            for comparison in [
+                Comparison::Equal(x),
+                Comparison::GreaterThan(x),
+                Comparison::GreaterThanOrEqual(x),
+                Comparison::LowerThan(x),
+                Comparison::LowerThanOrEqual(x),
+                Comparison::NotEqual(x),
+                Comparison::Range { low: x, high: x },
+                Comparison::Range {
+                    low: x,
+                    high: x + 2,
+                },
+            ] {
+                values.push(MaybeImprecise::Imprecise(comparison));
+            }
+        }
+        values
+    }
+
+    #[test]
+    fn test_compare_against_oracle() {
+        let mut mismatches = vec![];
+        for value in values() {
+            for n in 0..20 {
+                let checks = [
+                    (">", Compare::gt(&value, n), oracle(&value, |x| x > n)),
+                    (">=", Compare::gt_eq(&value, n), oracle(&value, |x| x >= n)),
+                    ("<", Compare::lt(&value, n), oracle(&value, |x| x < n)),
+                    ("<=", Compare::lt_eq(&value, n), oracle(&value, |x| x <= n)),
+                    ("=", Compare::eq(&value, n), oracle(&value, |x| x == n)),
+                    ("!=", Compare::ne(&value, n), oracle(&value, |x| x != n)),
+                    (
+                        "between",
+                        Compare::between(&value, n, n + 3),
+                        oracle(&value, |x| (n..=n + 3).contains(&x)),
+                    ),
+                    (
+                        "between",
+                        Compare::between(&value, n, n),
+                        oracle(&value, |x| x == n),
+                    ),
+                ];
+                for (operator, result, expected) in checks {
+                    if result != expected {
+                        mismatches.push(format!(
+                            "({value}) {operator} {n} gave {result:?} instead of {expected:?}"
+                        ));
+                    }
+                }
+            }
+        }
+        assert!(mismatches.is_empty(), "{mismatches:#?}");
+    }
 }
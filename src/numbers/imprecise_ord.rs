@@ -7,7 +7,8 @@ impl<T: ImpreciseOrd<T>> ImpreciseOrd<Self> for Option<T> {
         match (self, other) {
             (Some(x), Some(y)) => x.imprecise_cmp(y),
             (Some(_), None) => Ordering::Greater,
-            (None, Some(_) | None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => Ordering::Equal,
         }
     }
 }
@@ -20,58 +21,32 @@ impl<U, T: ImpreciseOrd<U>> ImpreciseOrd<&U> for &T {
 
 impl ImpreciseOrd<Self> for Comparison {
     fn imprecise_cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Self::GreaterThan(x), Self::GreaterThan(y) | Self::Equal(y) | Self::NotEqual(y)) => {
-                x.cmp(y)
-            }
-            (
-                Self::GreaterThanOrEqual(x),
-                Self::GreaterThanOrEqual(y)
-                | Self::LowerThanOrEqual(y)
-                | Self::GreaterThan(y)
-                | Self::Equal(y),
-            )
-            | (
-                Self::Equal(x),
-                Self::Equal(y) | Self::GreaterThanOrEqual(y) | Self::LowerThanOrEqual(y),
-            ) => x.cmp(y),
-            (Self::LowerThan(x), Self::LowerThan(y) | Self::Equal(y) | Self::NotEqual(y)) => {
-                x.cmp(y).reverse()
-            }
-            (
-                Self::LowerThanOrEqual(x),
-                Self::LowerThanOrEqual(y)
-                | Self::GreaterThanOrEqual(y)
-                | Self::LowerThan(y)
-                | Self::Equal(y),
-            ) => x.cmp(y).reverse(),
-            (Self::NotEqual(_), Self::Equal(_)) => Ordering::Less,
-            (Self::NotEqual(_), _) => Ordering::Equal,
-            _ => Ordering::Less,
-        }
+        let (self_low, self_high) = self.bounds();
+        let (other_low, other_high) = other.bounds();
+        self_low
+            .cmp(&other_low)
+            .then_with(|| match (self_high, other_high) {
+                (Some(x), Some(y)) => x.cmp(&y),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => Ordering::Equal,
+            })
     }
 }
 
-// impl ImpreciseOrd<usize> for Comparison {
-//     fn imprecise_cmp(&self, other: &usize) -> Ordering {
-//         match self {
-//             Self::GreaterThan(x) => {
-//                 if other >= x {
-//                     x.cmp(other)
-//                 } else {
-//                     Ordering::Less
-//                 }
-//             }
-//         }
-//     }
-// }
+/// Compares the comparison with the number as if the number were an `Equal` comparison. So `>3` is greater than 3, and so is `>=3`, since it also admits bigger numbers.
+impl ImpreciseOrd<usize> for Comparison {
+    fn imprecise_cmp(&self, other: &usize) -> Ordering {
+        self.imprecise_cmp(&Self::Equal(*other))
+    }
+}
 
-// // Reverse
-// impl ImpreciseOrd<Comparison> for usize {
-//     fn imprecise_cmp(&self, other: &Comparison) -> Ordering {
-//         other.imprecise_cmp(self)
-//     }
-// }
+// Reverse
+impl ImpreciseOrd<Comparison> for usize {
+    fn imprecise_cmp(&self, other: &Comparison) -> Ordering {
+        other.imprecise_cmp(self).reverse()
+    }
+}
 
 impl ImpreciseOrd<Self> for MaybeVar {
     fn imprecise_cmp(&self, other: &Self) -> Ordering {
@@ -79,18 +54,18 @@ impl ImpreciseOrd<Self> for MaybeVar {
     }
 }
 
-// impl ImpreciseOrd<usize> for MaybeVar {
-//     fn imprecise_cmp(&self, other: &usize) -> Ordering {
-//         self.assume() == *other
-//     }
-// }
+impl ImpreciseOrd<usize> for MaybeVar {
+    fn imprecise_cmp(&self, other: &usize) -> Ordering {
+        self.assume().cmp(other)
+    }
+}
 
-// // Reverse
-// impl ImpreciseOrd<MaybeVar> for usize {
-//     fn imprecise_cmp(&self, other: &MaybeVar) -> Ordering {
-//         other.imprecise_cmp(self)
-//     }
-// }
+// Reverse
+impl ImpreciseOrd<MaybeVar> for usize {
+    fn imprecise_cmp(&self, other: &MaybeVar) -> Ordering {
+        other.imprecise_cmp(self).reverse()
+    }
+}
 
 impl ImpreciseOrd<MaybeVar> for Comparison {
     fn imprecise_cmp(&self, other: &MaybeVar) -> Ordering {
@@ -108,9 +83,8 @@ impl ImpreciseOrd<Comparison> for MaybeVar {
 impl ImpreciseOrd<Self> for MaybeImprecise {
     fn imprecise_cmp(&self, other: &Self) -> Ordering {
         match (self, other) {
-            (Self::Precise(x), Self::Imprecise(y)) | (Self::Imprecise(y), Self::Precise(x)) => {
-                x.imprecise_cmp(y)
-            }
+            (Self::Precise(x), Self::Imprecise(y)) => x.imprecise_cmp(y),
+            (Self::Imprecise(x), Self::Precise(y)) => x.imprecise_cmp(y),
             (Self::Precise(x), Self::Precise(y)) => x.imprecise_cmp(y),
             (Self::Imprecise(x), Self::Imprecise(y)) => x.imprecise_cmp(y),
         }
@@ -133,21 +107,22 @@ impl ImpreciseOrd<MaybeImprecise> for Comparison {
     }
 }
 
-// impl ImpreciseOrd<usize> for MaybeImprecise {
-//     fn imprecise_cmp(&self, other: &usize) -> Ordering {
-//         match self {
-//             Self::Precise(x) => x.imprecise_cmp(other),
-//             Self::Imprecise(x) => x.imprecise_cmp(other),
-//         }
-//     }
-// }
+impl ImpreciseOrd<usize> for MaybeImprecise {
+    fn imprecise_cmp(&self, other: &usize) -> Ordering {
+        match self {
+            Self::Precise(x) => x.imprecise_cmp(other),
+            Self::Imprecise(x) => x.imprecise_cmp(other),
+        }
+    }
+}
+
+// Reverse
+impl ImpreciseOrd<MaybeImprecise> for usize {
+    fn imprecise_cmp(&self, other: &MaybeImprecise) -> Ordering {
+        other.imprecise_cmp(self).reverse()
+    }
+}
 
-// // Reverse
-// impl ImpreciseOrd<MaybeImprecise> for usize {
-//     fn imprecise_cmp(&self, other: &MaybeImprecise) -> Ordering {
-//         other.imprecise_cmp(self)
-//     }
-// }
 // impl ImpreciseOrd<MaybeVar> for MaybeImprecise {
 //     fn imprecise_cmp(&self, other: &MaybeVar) -> Ordering {
 //         match self {
@@ -163,3 +138,196 @@ impl ImpreciseOrd<MaybeImprecise> for Comparison {
 //         other.imprecise_cmp(self)
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use std::cmp::Ordering;
+
+    use crate::numbers::{Comparison, ImpreciseOrd, MaybeImprecise, MaybeVar};
+
+    fn all_comparisons() -> Vec<Comparison> {
+        (0..5)
+            .flat_map(|x| {
+                [
+                    Comparison::Equal(x),
+                    Comparison::GreaterThan(x),
+                    Comparison::GreaterThanOrEqual(x),
+                    Comparison::LowerThan(x),
+                    Comparison::LowerThanOrEqual(x),
+                    Comparison::NotEqual(x),
+                ]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_comparison_order_examples() {
+        let cases = [
+            (Comparison::Equal(3), Comparison::Equal(4), Ordering::Less),
+            (
+                Comparison::GreaterThan(3),
+                Comparison::Equal(4),
+                Ordering::Greater,
+            ),
+            (
+                Comparison::GreaterThan(3),
+                Comparison::Equal(5),
+                Ordering::Less,
+            ),
+            (
+                Comparison::GreaterThanOrEqual(4),
+                Comparison::Equal(4),
+                Ordering::Greater,
+            ),
+            (
+                Comparison::LowerThan(3),
+                Comparison::Equal(2),
+                Ordering::Less,
+            ),
+            (
+                Comparison::LowerThan(3),
+                Comparison::LowerThan(5),
+                Ordering::Less,
+            ),
+            (
+                Comparison::LowerThan(3),
+                Comparison::LowerThanOrEqual(2),
+                Ordering::Equal,
+            ),
+            (
+                Comparison::LowerThanOrEqual(9),
+                Comparison::Equal(1),
+                Ordering::Less,
+            ),
+            (
+                Comparison::NotEqual(3),
+                Comparison::Equal(0),
+                Ordering::Greater,
+            ),
+            (
+                Comparison::NotEqual(0),
+                Comparison::GreaterThan(0),
+                Ordering::Equal,
+            ),
+            (
+                Comparison::NotEqual(3),
+                Comparison::LowerThan(2),
+                Ordering::Greater,
+            ),
+        ];
+        for (a, b, expected) in cases {
+            assert_eq!(a.imprecise_cmp(&b), expected, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn test_comparison_order_is_total() {
+        let comparisons = all_comparisons();
+        for a in &comparisons {
+            assert_eq!(a.imprecise_cmp(a), Ordering::Equal, "{a} vs itself");
+            for b in &comparisons {
+                assert_eq!(
+                    a.imprecise_cmp(b),
+                    b.imprecise_cmp(a).reverse(),
+                    "{a} vs {b} is not antisymmetric"
+                );
+                for c in &comparisons {
+                    if a.imprecise_cmp(b) != Ordering::Greater
+                        && b.imprecise_cmp(c) != Ordering::Greater
+                    {
+                        assert_ne!(
+                            a.imprecise_cmp(c),
+                            Ordering::Greater,
+                            "{a} <= {b} <= {c} is not transitive"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_maybe_imprecise_order_is_antisymmetric() {
+        let precise = MaybeImprecise::Precise(MaybeVar::Const(3));
+        for comparison in all_comparisons() {
+            let imprecise = MaybeImprecise::Imprecise(comparison);
+            assert_eq!(
+                precise.imprecise_cmp(&imprecise),
+                imprecise.imprecise_cmp(&precise).reverse(),
+                "3 vs {comparison}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_sorting_mixed_stats() {
+        let mut stats = vec![
+            Some(MaybeImprecise::Imprecise(Comparison::GreaterThan(3))),
+            None,
+            Some(MaybeImprecise::Precise(MaybeVar::Const(4))),
+            Some(MaybeImprecise::Imprecise(Comparison::LowerThan(2))),
+            None,
+            Some(MaybeImprecise::Precise(MaybeVar::Const(2))),
+        ];
+        stats.sort_by(ImpreciseOrd::imprecise_cmp);
+        assert_eq!(
+            stats,
+            vec![
+                None,
+                None,
+                Some(MaybeImprecise::Imprecise(Comparison::LowerThan(2))),
+                Some(MaybeImprecise::Precise(MaybeVar::Const(2))),
+                Some(MaybeImprecise::Precise(MaybeVar::Const(4))),
+                Some(MaybeImprecise::Imprecise(Comparison::GreaterThan(3))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compare_to_usize() {
+        let cases = [
+            (Comparison::Equal(3), Ordering::Equal),
+            (Comparison::Equal(4), Ordering::Greater),
+            (Comparison::GreaterThan(3), Ordering::Greater),
+            (Comparison::GreaterThan(1), Ordering::Less),
+            (Comparison::GreaterThanOrEqual(3), Ordering::Greater),
+            (Comparison::GreaterThanOrEqual(4), Ordering::Greater),
+            (Comparison::LowerThan(3), Ordering::Less),
+            (Comparison::LowerThan(5), Ordering::Less),
+            (Comparison::LowerThanOrEqual(3), Ordering::Less),
+            (Comparison::NotEqual(3), Ordering::Less),
+            (Comparison::Range { low: 3, high: 5 }, Ordering::Greater),
+            (Comparison::Range { low: 1, high: 2 }, Ordering::Less),
+        ];
+        for (comparison, expected) in cases {
+            assert_eq!(comparison.imprecise_cmp(&3), expected, "{comparison} vs 3");
+            assert_eq!(
+                3.imprecise_cmp(&comparison),
+                expected.reverse(),
+                "3 vs {comparison}"
+            );
+            assert_eq!(
+                MaybeImprecise::Imprecise(comparison).imprecise_cmp(&3),
+                expected,
+                "{comparison} vs 3"
+            );
+            assert_eq!(
+                comparison.imprecise_cmp(&3),
+                comparison.imprecise_cmp(&Comparison::Equal(3)),
+                "{comparison} vs 3"
+            );
+        }
+
+        assert_eq!(MaybeVar::Const(2).imprecise_cmp(&3), Ordering::Less);
+        assert_eq!(MaybeVar::Var('X').imprecise_cmp(&0), Ordering::Equal);
+        assert_eq!(3.imprecise_cmp(&MaybeVar::Const(2)), Ordering::Greater);
+        assert_eq!(
+            MaybeImprecise::Precise(MaybeVar::Const(3)).imprecise_cmp(&3),
+            Ordering::Equal
+        );
+        assert_eq!(
+            4.imprecise_cmp(&MaybeImprecise::Precise(MaybeVar::Const(3))),
+            Ordering::Greater
+        );
+    }
+}
@@ -1,23 +1,29 @@
+pub mod autocomplete;
+mod canonical;
+pub mod compiled;
+pub mod devours;
 pub mod fuzzy;
 pub mod query_parser;
 use std::{
     cell::RefCell,
-    cmp::{max, min},
-    collections::HashMap,
+    cmp::{max, min, Reverse},
+    collections::{HashMap, HashSet},
     fmt::{Display, Write},
     ops::Not,
 };
 
-use fuzzy::weighted_compare;
+use fuzzy::{weighted_compare, FuzzyScope, SynonymTable};
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     cards::{
         properties::{Array, Number, Read, Text},
-        Keyword, KeywordData,
+        rich_text::{RichElement, RichString},
+        Card, CardId, Keyword, KeywordData,
     },
     clean_ascii,
-    numbers::{Comparison, ImpreciseOrd},
+    numbers::{Comparison, ImpreciseOrd, MaybeImprecise, MaybeVar},
 };
 
 /// Errors that might happen during searching
@@ -28,6 +34,9 @@ pub enum Errors {
     InvalidComparisonString,
     UnknownSubQueryParam(String),
     UnknownStringParam(String),
+    UnknownIsParam(String),
+    UnknownMissingParam(String),
+    UnknownHasParam(String),
     InvalidOrdering(String),
     InvalidPolarity,
     NotSortable,
@@ -36,6 +45,7 @@ pub enum Errors {
     UnclosedRegex,
     RegexErr(regex::Error),
     AttemptedEmptyParamName,
+    EmptyKeywordName,
 }
 
 /// Represents whether a query has been matched or not. This is not always a boolean value, but instead a ternary value, as cards may have undefined properties.
@@ -108,7 +118,7 @@ impl From<bool> for Ternary {
 }
 
 /// Represents a search query
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Query {
     pub name: String,
     pub restrictions: Vec<QueryRestriction>,
@@ -133,6 +143,11 @@ impl Display for Query {
             Sort::Numeric(property, order) => {
                 text_properties.push(format!("sorted by {property} in {order} order"));
             }
+            Sort::NumericMidpoint(property, order) => {
+                text_properties.push(format!(
+                    "sorted by the middle of their {property} in {order} order"
+                ));
+            }
         }
 
         let text_properties = text_properties.into_iter().reduce(|mut acc, el| {
@@ -146,6 +161,31 @@ impl Display for Query {
     }
 }
 
+/// Displays text in double quotes, escaping quotes and backslashes the way the query parser reads them back.
+struct Quoted<'a>(&'a str);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('"')?;
+        for ch in self.0.chars() {
+            if matches!(ch, '"' | '\\') {
+                f.write_char('\\')?;
+            }
+            f.write_char(ch)?;
+        }
+        f.write_char('"')
+    }
+}
+
+/// Displays a regex between slashes, escaping the slashes in its pattern the way the query parser reads them back.
+struct Slashed<'a>(&'a Regex);
+
+impl Display for Slashed<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "/{}/", self.0.as_str().replace('/', "\\/"))
+    }
+}
+
 #[allow(clippy::match_wildcard_for_single_variants)]
 impl Display for QueryRestriction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -153,22 +193,92 @@ impl Display for QueryRestriction {
             Self::DevouredBy(devourer) => {
                 write!(f, "which are devoured by [{devourer}]")
             }
-            Self::Fuzzy(text) => write!(f, "with \"{text}\" written on them"),
+            Self::Fuzzy(text) => write!(f, "with {} written on them", Quoted(text)),
             Self::Devours(devourees) => write!(f, "that devour [{devourees}]"),
             Self::Comparison(property, comparison) => {
                 write!(f, "with {property} {comparison}")
             }
+            Self::IsVariable(property) => write!(f, "whose {property} is a variable"),
+            Self::Within(property, other, tolerance) => {
+                write!(f, "with {property} within {tolerance} of their {other}")
+            }
+            Self::FieldComparison(property, op, other) => {
+                write!(f, "with {property} {op} their {other}")
+            }
             Self::Contains(property, text) => {
-                write!(f, "whose {property} contains \"{text}\"")
+                write!(f, "whose {property} contains {}", Quoted(text))
             }
+            Self::Equals(property, text) => write!(f, "whose {property} is {}", Quoted(text)),
             Self::Regex(property, regex) => {
-                write!(f, "whose {property} matches /{regex}/")
+                write!(f, "whose {property} matches {}", Slashed(regex))
             }
             Self::Has(property, text) => match property {
-                Array::Functions => write!(f, "which can be used to \"{text}\""),
-                property => write!(f, "whose {property} have \"{text}\" among them"),
+                Array::Functions => write!(f, "which can be used to {}", Quoted(text)),
+                Array::Abilities => write!(f, "with an ability that mentions {}", Quoted(text)),
+                property => write!(f, "whose {property} have {} among them", Quoted(text)),
             },
-            Self::HasKw(keyword) => write!(f, "with a \"{keyword}\" keyword"),
+            Self::HasExact(property, text) => {
+                write!(f, "whose {property} include {}", Quoted(text))
+            }
+            Self::HasRegex(property, regex) => {
+                write!(f, "whose {property} have one matching {}", Slashed(regex))
+            }
+            Self::HasKw(keyword) => write!(f, "with a {} keyword", Quoted(keyword)),
+            Self::HasKwExact(keyword) => {
+                write!(f, "with a keyword named exactly {}", Quoted(keyword))
+            }
+            Self::HasKwData(text) => {
+                write!(f, "with a keyword whose data contains {}", Quoted(text))
+            }
+            Self::KwSet(keywords) => {
+                let mut keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+                keywords.sort_unstable();
+                write!(f, "whose keywords are exactly [{}]", keywords.join(", "))
+            }
+            Self::KwNumber(keyword, comparison) => {
+                write!(
+                    f,
+                    "with a {} keyword whose number is {comparison}",
+                    Quoted(keyword)
+                )
+            }
+            Self::HasKwWithData(keyword, data) => {
+                write!(
+                    f,
+                    "with a {} keyword whose data is {}",
+                    Quoted(keyword),
+                    Quoted(data)
+                )
+            }
+            Self::HasKwDataRegex(regex) => {
+                write!(f, "with a keyword whose data matches {}", Slashed(regex))
+            }
+            Self::HasArtist => write!(f, "which credit an artist"),
+            Self::HasImpreciseStat => write!(f, "with an imprecise or variable stat"),
+            Self::IsReprint => write!(f, "which are reprints"),
+            Self::IsAggressive => write!(f, "with more power than health"),
+            Self::IsDefensive => write!(f, "with more health than power"),
+            Self::MissingFlavorText => write!(f, "without flavor text"),
+            Self::HasNumProperty(property) => write!(f, "which have {property}"),
+            Self::HasTextProperty(property) => write!(f, "which have {property}"),
+            Self::HasArrayProperty(property) => write!(f, "which have {property}"),
+            Self::LinksTo(id) => write!(f, "which link to {}", Quoted(id)),
+            Self::HasStatus(status) => write!(f, "which are {} in some format", Quoted(status)),
+            Self::LegalIn(format) => write!(f, "which are legal in {}", Quoted(format)),
+            Self::DescriptionWords(comparison) => {
+                write!(f, "whose description has {comparison} words")
+            }
+            Self::Count(property, comparison) => {
+                write!(f, "with {comparison} {property}")
+            }
+            Self::IdIn(ids) => {
+                let mut ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+                ids.sort_unstable();
+                write!(f, "whose id is one of [{}]", ids.join(", "))
+            }
+            Self::DevourChainKins(comparison) => {
+                write!(f, "whose devour chain has {comparison} kins")
+            }
             Self::Not(query) => write!(f, "that aren't [{query}]"),
             Self::LenientNot(query) => write!(
                 f,
@@ -182,16 +292,71 @@ impl Display for QueryRestriction {
 }
 
 /// Represents a specific restriction that a `Query` will apply to cards.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "args")]
 pub enum QueryRestriction {
     Fuzzy(String),
     Devours(Query),
     DevouredBy(Query),
     Comparison(Number, Comparison),
+    /// Matches if the property is a variable like `X`, or a variable plus an offset like `X+1`, whatever number it's assumed to be.
+    IsVariable(Number),
+    /// Matches if the difference between the assumed values of both properties is at most the given number.
+    Within(Number, Number, usize),
+    /// Compares two of the card's numeric properties with each other, like its power with its health. Variables count as what they're assumed to be, and imprecise values are compared by their bounds with `ImpreciseOrd`, so `>2` is greater than 3 but lower than 4. `Void` if the card lacks either property, like commands do.
+    FieldComparison(Number, ComparisonOp, Number),
     Contains(Text, String),
-    Regex(Text, Regex),
+    /// Matches if the property is exactly this text, ignoring casing and diaereses.
+    Equals(Text, String),
+    Regex(Text, #[serde(with = "regex_serde")] Regex),
     Has(Array, String),
+    /// Matches if one of the array's elements is exactly this text, ignoring casing and diaereses.
+    HasExact(Array, String),
+    /// Matches if one of the array's elements matches this regex.
+    HasRegex(Array, #[serde(with = "regex_serde")] Regex),
     HasKw(String),
+    /// Matches if the card has a keyword with exactly this name, ignoring casing and diaereses.
+    HasKwExact(String),
+    HasKwData(String),
+    /// Matches if the card has a keyword with exactly this name, whose data is exactly this string or a `CardId` with exactly this name. Both are compared ignoring casing and diaereses.
+    HasKwWithData(String, String),
+    /// Matches if the names of the card's keywords are exactly these, in any order, ignoring casing and diaereses.
+    KwSet(HashSet<String>),
+    /// Compares the number in the data of the card's keywords with exactly this name, like the `3` in `armor 3`. `Void` if the card has no such keyword or its data isn't a number.
+    KwNumber(String, Comparison),
+    #[serde(with = "regex_serde")]
+    HasKwDataRegex(Regex),
+    HasArtist,
+    /// Matches if any of the card's numeric properties is imprecise or a variable. Never `Void`, as it looks at the whole card.
+    HasImpreciseStat,
+    /// Compares the number of words in the card's description.
+    DescriptionWords(Comparison),
+    /// Compares the number of elements in one of the card's array properties, like how many abilities it has.
+    Count(Array, Comparison),
+    /// Matches if another card in the pool has the same name but a different id.
+    IsReprint,
+    /// Matches if the card's power is greater than its health. Same as `p>h`, so a power of `>2` is greater than a health of 2 or 3. `Void` if the card lacks either stat, like commands do.
+    IsAggressive,
+    /// Matches if the card's health is greater than its power. Same as `p<h`.
+    IsDefensive,
+    /// Matches if the card has this numeric property at all, whatever its value. Never `Void`: a card without the property, like a command's power, is `False`.
+    HasNumProperty(Number),
+    /// Matches if the card has this text property at all, even if it's empty. Never `Void`, like `HasNumProperty`.
+    HasTextProperty(Text),
+    /// Matches if the card has this array property at all, even if it has no elements. Never `Void`, like `HasNumProperty`.
+    HasArrayProperty(Array),
+    /// Matches if the card's flavor text is empty. `Void` for cards that can't have flavor text, like `CardId`s, since it's unknown whether the card they point to has it.
+    MissingFlavorText,
+    /// Matches if the card's id is one of these.
+    IdIn(HashSet<String>),
+    /// Matches if the card's description links to the card with this id, or to an identity with this name.
+    LinksTo(String),
+    /// Matches if the card's legality in any format is this status, like "banned". `Void` for cards without legality, like `CardId`s.
+    HasStatus(String),
+    /// Matches if the card's legality in this format is "legal", ignoring casing. Cards that don't list the format, or list it with another status like "banned", don't match. `Void` for cards without legality, like `CardId`s.
+    LegalIn(String),
+    /// Compares the number of distinct kins among the cards a card devours, directly or through the cards it devours.
+    DevourChainKins(Comparison),
     Not(Query),
     LenientNot(Query),
     Group(Query),
@@ -199,8 +364,124 @@ pub enum QueryRestriction {
     Xor(Query, Query),
 }
 
+/// Regexes are considered equal if their patterns are the same.
+impl PartialEq for QueryRestriction {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Fuzzy(a), Self::Fuzzy(b))
+            | (Self::HasKw(a), Self::HasKw(b))
+            | (Self::HasKwExact(a), Self::HasKwExact(b))
+            | (Self::LinksTo(a), Self::LinksTo(b))
+            | (Self::HasStatus(a), Self::HasStatus(b))
+            | (Self::LegalIn(a), Self::LegalIn(b))
+            | (Self::HasKwData(a), Self::HasKwData(b)) => a == b,
+            (Self::Devours(a), Self::Devours(b))
+            | (Self::DevouredBy(a), Self::DevouredBy(b))
+            | (Self::Not(a), Self::Not(b))
+            | (Self::LenientNot(a), Self::LenientNot(b))
+            | (Self::Group(a), Self::Group(b)) => a == b,
+            (Self::Or(a1, a2), Self::Or(b1, b2)) | (Self::Xor(a1, a2), Self::Xor(b1, b2)) => {
+                a1 == b1 && a2 == b2
+            }
+            (Self::Comparison(a, x), Self::Comparison(b, y)) => a == b && x == y,
+            (Self::IsVariable(a), Self::IsVariable(b))
+            | (Self::HasNumProperty(a), Self::HasNumProperty(b)) => a == b,
+            (Self::Count(a, x), Self::Count(b, y)) => a == b && x == y,
+            (Self::Within(a1, a2, x), Self::Within(b1, b2, y)) => a1 == b1 && a2 == b2 && x == y,
+            (Self::FieldComparison(a1, x, a2), Self::FieldComparison(b1, y, b2)) => {
+                a1 == b1 && a2 == b2 && x == y
+            }
+            (Self::Contains(a, x), Self::Contains(b, y))
+            | (Self::Equals(a, x), Self::Equals(b, y)) => a == b && x == y,
+            (Self::Regex(a, x), Self::Regex(b, y)) => a == b && x.as_str() == y.as_str(),
+            (Self::Has(a, x), Self::Has(b, y)) | (Self::HasExact(a, x), Self::HasExact(b, y)) => {
+                a == b && x == y
+            }
+            (Self::HasRegex(a, x), Self::HasRegex(b, y)) => a == b && x.as_str() == y.as_str(),
+            (Self::HasKwWithData(a, x), Self::HasKwWithData(b, y)) => a == b && x == y,
+            (Self::KwNumber(a, x), Self::KwNumber(b, y)) => a == b && x == y,
+            (Self::HasKwDataRegex(x), Self::HasKwDataRegex(y)) => x.as_str() == y.as_str(),
+            (Self::HasArtist, Self::HasArtist)
+            | (Self::HasImpreciseStat, Self::HasImpreciseStat)
+            | (Self::IsReprint, Self::IsReprint)
+            | (Self::IsAggressive, Self::IsAggressive)
+            | (Self::IsDefensive, Self::IsDefensive)
+            | (Self::MissingFlavorText, Self::MissingFlavorText) => true,
+            (Self::DevourChainKins(x), Self::DevourChainKins(y))
+            | (Self::DescriptionWords(x), Self::DescriptionWords(y)) => x == y,
+            (Self::IdIn(x), Self::IdIn(y)) | (Self::KwSet(x), Self::KwSet(y)) => x == y,
+            (Self::HasTextProperty(a), Self::HasTextProperty(b)) => a == b,
+            (Self::HasArrayProperty(a), Self::HasArrayProperty(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for QueryRestriction {}
+
+impl QueryRestriction {
+    /// Builds a restriction that matches if any of the `restrictions` match, as a balanced tree of `Or`s. With no restrictions, it never matches.
+    #[must_use]
+    pub fn any(mut restrictions: Vec<Self>) -> Self {
+        match restrictions.len() {
+            0 => Self::Not(Query::from_restrictions(vec![])),
+            1 => restrictions.remove(0),
+            len => {
+                let right = restrictions.split_off(len / 2);
+                Self::Or(
+                    Query::from_restrictions(vec![Self::any(restrictions)]),
+                    Query::from_restrictions(vec![Self::any(right)]),
+                )
+            }
+        }
+    }
+
+    /// Builds a restriction that matches if all of the `restrictions` match, as a `Group`. With no restrictions, it always matches.
+    #[must_use]
+    pub fn all(mut restrictions: Vec<Self>) -> Self {
+        if restrictions.len() == 1 {
+            restrictions.remove(0)
+        } else {
+            Self::Group(Query::from_restrictions(restrictions))
+        }
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self::from_restrictions(vec![])
+    }
+}
+
+impl Query {
+    /// Creates a nameless, unsorted query with these `restrictions`.
+    const fn from_restrictions(restrictions: Vec<QueryRestriction>) -> Self {
+        Self {
+            name: String::new(),
+            restrictions,
+            sort: Sort::None,
+        }
+    }
+
+    /// Also requires the card to be in the `set`, ignoring casing and diaereses.
+    #[must_use]
+    pub fn in_set(mut self, set: &str) -> Self {
+        self.restrictions
+            .push(QueryRestriction::Equals(Text::Set, set.to_owned()));
+        self
+    }
+
+    /// Also requires the card to have the `tag` among its other tags, ignoring casing and diaereses.
+    #[must_use]
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.restrictions
+            .push(QueryRestriction::HasExact(Array::Other, tag.to_owned()));
+        self
+    }
+}
+
 /// Represents a specific ordering for sorting.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Ordering {
     Ascending,
     Descending,
@@ -215,8 +496,59 @@ impl Display for Ordering {
     }
 }
 
+impl Ordering {
+    /// Returns the opposite ordering.
+    #[must_use]
+    pub const fn reverse(self) -> Self {
+        match self {
+            Self::Ascending => Self::Descending,
+            Self::Descending => Self::Ascending,
+        }
+    }
+}
+
+/// An operator that compares two values, like the `>` in `power>health`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComparisonOp {
+    GreaterThan,
+    GreaterThanOrEqual,
+    LowerThan,
+    LowerThanOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Display for ComparisonOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GreaterThan => write!(f, "greater than"),
+            Self::GreaterThanOrEqual => write!(f, "greater than or equal to"),
+            Self::LowerThan => write!(f, "lower than"),
+            Self::LowerThanOrEqual => write!(f, "lower than or equal to"),
+            Self::Equal => write!(f, "equal to"),
+            Self::NotEqual => write!(f, "other than"),
+        }
+    }
+}
+
+impl ComparisonOp {
+    /// Checks whether a comparison that resulted in `ordering` satisfies the operator.
+    #[must_use]
+    pub const fn accepts(self, ordering: std::cmp::Ordering) -> bool {
+        match self {
+            Self::GreaterThan => ordering.is_gt(),
+            Self::GreaterThanOrEqual => ordering.is_ge(),
+            Self::LowerThan => ordering.is_lt(),
+            Self::LowerThanOrEqual => ordering.is_le(),
+            Self::Equal => ordering.is_eq(),
+            Self::NotEqual => ordering.is_ne(),
+        }
+    }
+}
+
 /// Specific ways to sort cards.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "args")]
 pub enum Sort {
     /// Do not sort
     None,
@@ -224,89 +556,528 @@ pub enum Sort {
     Fuzzy,
     Alphabet(Text, Ordering),
     Numeric(Number, Ordering),
+    /// Sort by a numeric property, ranking imprecise values by the middle of the numbers they admit
+    NumericMidpoint(Number, Ordering),
+}
+
+impl Sort {
+    /// Returns the same sort in the opposite order. `None` and `Fuzzy` have no order to flip, so they are returned as they are.
+    #[must_use]
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::None => Self::None,
+            Self::Fuzzy => Self::Fuzzy,
+            Self::Alphabet(property, order) => Self::Alphabet(property, order.reverse()),
+            Self::Numeric(property, order) => Self::Numeric(property, order.reverse()),
+            Self::NumericMidpoint(property, order) => {
+                Self::NumericMidpoint(property, order.reverse())
+            }
+        }
+    }
+}
+
+/// Serializes regexes as their pattern, so queries can be saved and loaded.
+mod regex_serde {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(regex.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(D::Error::custom)
+    }
 }
 
 /// Restriction that matches only if a card contains some text
 #[must_use]
 pub fn fuzzy(card: &impl Read, query: &str) -> bool {
-    card.get_description()
-        .is_some_and(|x| clean_ascii(&x.to_string()).contains(&clean_ascii(query)))
-        || card
-            .get_name()
-            .is_some_and(|x| clean_ascii(x).contains(&clean_ascii(query)))
-        || card
-            .get_type()
-            .is_some_and(|x| clean_ascii(x).contains(&clean_ascii(query)))
-        || card.get_kins().is_some_and(|x| {
-            x.iter()
-                .any(|x| clean_ascii(x).contains(&clean_ascii(query)))
-        })
-        || card.get_keywords().is_some_and(|x| {
-            x.iter()
-                .any(|x| clean_ascii(&x.name).contains(&clean_ascii(query)))
-        })
+    fuzzy_scoped(card, query, &FuzzyScope::default())
+}
+
+/// Does the same as `fuzzy`, but only looks at the texts the `scope` includes.
+#[must_use]
+pub fn fuzzy_scoped(card: &impl Read, query: &str, scope: &FuzzyScope) -> bool {
+    scope.description
+        && card
+            .get_description()
+            .is_some_and(|x| clean_ascii(&x.to_string()).contains(&clean_ascii(query)))
+        || scope.name
+            && card
+                .get_name()
+                .is_some_and(|x| clean_ascii(x).contains(&clean_ascii(query)))
+        || scope.r#type
+            && card
+                .get_type()
+                .is_some_and(|x| clean_ascii(x).contains(&clean_ascii(query)))
+        || scope.kins
+            && card.get_kins().is_some_and(|x| {
+                x.iter()
+                    .any(|x| clean_ascii(x).contains(&clean_ascii(query)))
+            })
+        || scope.keywords
+            && card.get_keywords().is_some_and(|x| {
+                x.iter()
+                    .any(|x| clean_ascii(&x.name).contains(&clean_ascii(query)))
+            })
+        || scope.flavor_text
+            && card
+                .get_flavor_text()
+                .is_some_and(|x| clean_ascii(x).contains(&clean_ascii(query)))
+        || scope.functions
+            && card.get_vec_property(&Array::Functions).is_some_and(|x| {
+                x.iter()
+                    .any(|x| clean_ascii(x).contains(&clean_ascii(query)))
+            })
 }
 
-/// The Cache for `devouredby` queries.
+/// Does the same as `fuzzy`, but also matches if any synonym expansion of `query` is contained in the card.
+#[must_use]
+pub fn fuzzy_with_synonyms(card: &impl Read, query: &str, synonyms: &SynonymTable) -> bool {
+    synonyms
+        .expand(query)
+        .iter()
+        .any(|query| fuzzy(card, query))
+}
+
+/// The Cache for `devouredby` queries. It only holds references to the cards in the pool, so caching results never clones the cards themselves.
 pub type Cache<T> = RefCell<HashMap<String, Vec<T>>>;
 
 /// Function that takes `cards` and outputs a vector pointing to all the cards that matched the `query`.
 #[must_use]
 pub fn search<'a, 'b, C, I>(query: &Query, cards: I) -> Vec<&'a C>
 where
-    C: Read + Clone + 'a,
+    C: Read + 'a,
     I: IntoIterator<Item = &'a C> + Clone + 'b,
     &'a C: Read,
 {
-    let cards_clone = cards.clone();
-    let cache = Cache::new(HashMap::new());
-    let mut results: Vec<&C> = cards
+    search_scored(query, cards)
+        .into_iter()
+        .map(|(card, _)| card)
+        .collect()
+}
+
+/// Does the same as `search`, but pairs each card with its relevance to the query's name. The score is the `weighted_compare` of the card and the name when the query is sorted by `Sort::Fuzzy`, and 0 otherwise.
+#[must_use]
+pub fn search_scored<'a, 'b, C, I>(query: &Query, cards: I) -> Vec<(&'a C, f32)>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone + 'b,
+    &'a C: Read,
+{
+    sort_matches(query, matching_cards(query, cards))
+}
+
+/// Scores the `matches` of the `query` and sorts them the way it asks, for `search_scored`.
+fn sort_matches<'a, C>(query: &Query, matches: Vec<&'a C>) -> Vec<(&'a C, f32)>
+where
+    C: Read + 'a,
+{
+    let scored = matches!(query.sort, Sort::Fuzzy) && !query.name.is_empty();
+    let mut results: Vec<(&C, f32)> = matches
         .into_iter()
-        .filter(|card| matches_query(card, query, &cards_clone, &cache) == Ternary::True)
+        .map(|card| {
+            let score = if scored {
+                weighted_compare(card, &query.name)
+            } else {
+                0.0
+            };
+            (card, score)
+        })
         .collect();
 
     match &query.sort {
         Sort::None => (),
-        Sort::Fuzzy if !query.name.is_empty() => results.sort_by(|a, b| {
-            weighted_compare(b, &query.name)
-                .partial_cmp(&weighted_compare(a, &query.name))
-                .unwrap_or(std::cmp::Ordering::Equal)
-        }),
-        Sort::Fuzzy => results.sort_by(|a, b| Ord::cmp(&a.get_name(), &b.get_name())),
-        Sort::Alphabet(property, Ordering::Ascending) => results.sort_by(|a, b| {
-            Ord::cmp(
-                &a.get_text_property(property),
-                &b.get_text_property(property),
-            )
-        }),
-        Sort::Numeric(property, Ordering::Ascending) => results.sort_by(|a, b| {
+        Sort::Fuzzy if scored => {
+            results.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+        }
+        Sort::Fuzzy => results.sort_by(|(a, _), (b, _)| Ord::cmp(&a.get_name(), &b.get_name())),
+        Sort::Alphabet(property, Ordering::Ascending) => {
+            results.sort_by_cached_key(|(card, _)| alphabetical_key(*card, *property));
+        }
+        Sort::Numeric(property, Ordering::Ascending) => results.sort_by(|(a, _), (b, _)| {
             ImpreciseOrd::imprecise_cmp(
                 &a.get_num_property(property),
                 &b.get_num_property(property),
             )
         }),
         Sort::Alphabet(property, Ordering::Descending) => {
-            results.sort_by(|a, b| {
-                Ord::cmp(
-                    &a.get_text_property(property),
-                    &b.get_text_property(property),
-                )
-                .reverse()
-            });
+            results.sort_by_cached_key(|(card, _)| Reverse(alphabetical_key(*card, *property)));
         }
-        Sort::Numeric(property, Ordering::Descending) => results.sort_by(|a, b| {
+        Sort::Numeric(property, Ordering::Descending) => results.sort_by(|(a, _), (b, _)| {
             ImpreciseOrd::imprecise_cmp(
                 &a.get_num_property(property),
                 &b.get_num_property(property),
             )
             .reverse()
         }),
+        Sort::NumericMidpoint(property, order) => results.sort_by(|(a, _), (b, _)| {
+            let rank = |card: &C| C::get_num_property(card, property).map(|x| x.rank_value());
+            let ordering = rank(a)
+                .partial_cmp(&rank(b))
+                .unwrap_or(std::cmp::Ordering::Equal);
+            match order {
+                Ordering::Ascending => ordering,
+                Ordering::Descending => ordering.reverse(),
+            }
+        }),
+    }
+
+    results
+}
+
+/// Does the same as `search`, but checks the cards in parallel.
+///
+/// The cache can't be shared between threads, so each batch of cards rayon hands to a thread gets its own. Whether a card is a reprint depends on the whole pool, so that is worked out once before splitting the cards up, and every cache starts with it. The restrictions about devouring, `devours`, `devouredby` and `DevourChainKins`, fill the cache with searches over the whole pool, so queries with any of them anywhere are searched sequentially instead.
+#[cfg(feature = "rayon")]
+#[must_use]
+pub fn search_par<'a, C>(query: &Query, cards: &'a [C]) -> Vec<&'a C>
+where
+    C: Read + Sync + 'a,
+    &'a C: Read,
+{
+    use rayon::prelude::*;
+
+    if any_restriction(&query.restrictions, &|restriction| {
+        matches!(
+            restriction,
+            QueryRestriction::Devours(_)
+                | QueryRestriction::DevouredBy(_)
+                | QueryRestriction::DevourChainKins(_)
+        )
+    }) {
+        return search(query, cards);
+    }
+    let mut shared: HashMap<String, Vec<&C>> = HashMap::new();
+    if any_restriction(&query.restrictions, &|restriction| {
+        matches!(restriction, QueryRestriction::IsReprint)
+    }) {
+        shared.insert(REPRINTS_KEY.to_owned(), reprinted_cards(cards));
     }
+    let matches: Vec<&C> = cards
+        .par_iter()
+        .map_init(
+            || Cache::new(shared.clone()),
+            |cache, card| (card, matches_query(card, query, &cards.iter(), cache)),
+        )
+        .filter(|(_, matches)| *matches == Ternary::True)
+        .map(|(card, _)| card)
+        .collect();
+    sort_matches(query, matches)
+        .into_iter()
+        .map(|(card, _)| card)
+        .collect()
+}
+
+/// Whether any of the `restrictions`, or the ones nested inside them, is one that `wanted` accepts. The subqueries of `devours` and `devouredby` are looked at too.
+#[cfg(feature = "rayon")]
+fn any_restriction(
+    restrictions: &[QueryRestriction],
+    wanted: &impl Fn(&QueryRestriction) -> bool,
+) -> bool {
+    restrictions.iter().any(|restriction| {
+        wanted(restriction)
+            || match restriction {
+                QueryRestriction::Devours(query)
+                | QueryRestriction::DevouredBy(query)
+                | QueryRestriction::Not(query)
+                | QueryRestriction::LenientNot(query)
+                | QueryRestriction::Group(query) => any_restriction(&query.restrictions, wanted),
+                QueryRestriction::Or(a, b) | QueryRestriction::Xor(a, b) => {
+                    any_restriction(&a.restrictions, wanted)
+                        || any_restriction(&b.restrictions, wanted)
+                }
+                _ => false,
+            }
+    })
+}
 
+/// Does the same as `search`, but sorts the results with `compare` instead of the query's sort. The comparator only runs after filtering, so it only sees cards that matched the `query`.
+#[must_use]
+pub fn search_sorted_by<'a, 'b, C, I, F>(query: &Query, cards: I, mut compare: F) -> Vec<&'a C>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone + 'b,
+    &'a C: Read,
+    F: FnMut(&C, &C) -> std::cmp::Ordering,
+{
+    let mut results = matching_cards(query, cards);
+    results.sort_by(|a, b| compare(a, b));
     results
 }
 
-/// This function checks whether a `card` matches a specific `query`'s restrictions. Since `devouredby` queries always require two searches, the results of the first search are stored in a `cache` that is internally mutable. This cache is only ever mutated the first time a devouredby query is executed.
+/// Returns the `cards` that match the `query`, in their original order.
+fn matching_cards<'a, 'b, C, I>(query: &Query, cards: I) -> Vec<&'a C>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone + 'b,
+    &'a C: Read,
+{
+    let pool = cards.clone();
+    matching_among(query, cards, &pool)
+}
+
+/// Returns the `candidates` that match the `query`, looking at all the `cards` for restrictions like `devouredby` that depend on the whole pool.
+fn matching_among<'a, C, I>(
+    query: &Query,
+    candidates: impl IntoIterator<Item = &'a C>,
+    cards: &I,
+) -> Vec<&'a C>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone,
+    &'a C: Read,
+{
+    let cache = Cache::new(HashMap::new());
+    candidates
+        .into_iter()
+        .filter(|card| matches_query(card, query, cards, &cache) == Ternary::True)
+        .collect()
+}
+
+/// Does the same as `search`, but only the `candidates` can be matched. Every card that could match should be among them, and they should be in the order they have in `cards`, so that the results are the same as with `search`.
+pub(crate) fn search_among<'a, C, I>(
+    query: &Query,
+    candidates: impl IntoIterator<Item = &'a C>,
+    cards: &I,
+) -> Vec<&'a C>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone,
+    &'a C: Read,
+{
+    sort_matches(query, matching_among(query, candidates, cards))
+        .into_iter()
+        .map(|(card, _)| card)
+        .collect()
+}
+
+/// Compares two of the `card`'s numeric properties with `op`, so that imprecise stats compare by their bounds. `Void` if the card lacks either property.
+fn compare_fields(card: &impl Read, field: Number, op: ComparisonOp, other: Number) -> Ternary {
+    match (card.get_num_property(&field), card.get_num_property(&other)) {
+        (Some(a), Some(b)) => op.accepts(a.imprecise_cmp(&b)).into(),
+        _ => Ternary::Void,
+    }
+}
+
+/// The key `Sort::Alphabet` sorts by: the property cleaned with `clean_ascii`, so that casing and diaereses don't move cards away from the letter they're read as, followed by the original text to break ties.
+fn alphabetical_key(card: &impl Read, property: Text) -> Option<(String, String)> {
+    card.get_text_property(&property)
+        .map(|text| (clean_ascii(&text), text))
+}
+
+/// Returns the label of every query in `labeled_queries` that the `card` matches, in order. This is the opposite of a search: one card is checked against many queries.
+///
+/// All queries share a single cache, so `devouredby` restrictions repeated across queries are only resolved against the `pool` once.
+#[must_use]
+pub fn labels_for_card<'a, 'l, C, T, I>(
+    card: &C,
+    labeled_queries: &[(&'l str, &Query)],
+    pool: &I,
+) -> Vec<&'l str>
+where
+    C: Read,
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T> + Clone,
+{
+    let cache = Cache::new(HashMap::new());
+    labeled_queries
+        .iter()
+        .filter(|(_, query)| matches_query(card, query, pool, &cache) == Ternary::True)
+        .map(|(label, _)| *label)
+        .collect()
+}
+
+/// Counts of how many cards have each distinct value of a text property, keyed by property.
+pub type Facets = HashMap<Text, HashMap<String, usize>>;
+
+/// Does the same as `search`, but also counts how many of the matched cards have each distinct value of the given `facets`.
+///
+/// Cards that don't have a facet's property are not counted for that facet.
+#[must_use]
+pub fn search_with_facets<'a, 'b, C, I>(
+    query: &Query,
+    cards: I,
+    facets: &[Text],
+) -> (Vec<&'a C>, Facets)
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone + 'b,
+    &'a C: Read,
+{
+    let results = search(query, cards);
+    let mut counts: Facets = facets
+        .iter()
+        .map(|facet| (*facet, HashMap::new()))
+        .collect();
+
+    for card in &results {
+        for facet in facets {
+            if let Some(value) = card.get_text_property(facet) {
+                *counts.entry(*facet).or_default().entry(value).or_insert(0) += 1;
+            }
+        }
+    }
+
+    (results, counts)
+}
+
+/// How much a card's relevance grows for each restriction it matches, depending on the text property the restriction looks at.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelevanceWeights {
+    /// The weight of restrictions on each text property, like `name:` or `description:`.
+    pub text: HashMap<Text, f64>,
+    /// The weight of text properties missing from `text`, and of every other kind of restriction.
+    pub other: f64,
+}
+
+impl Default for RelevanceWeights {
+    fn default() -> Self {
+        Self {
+            text: HashMap::from([
+                (Text::Name, 4.0),
+                (Text::Type, 2.0),
+                (Text::Description, 1.0),
+                (Text::FlavorText, 0.5),
+            ]),
+            other: 1.0,
+        }
+    }
+}
+
+impl RelevanceWeights {
+    fn weight(&self, restriction: &QueryRestriction) -> f64 {
+        match restriction {
+            QueryRestriction::Contains(property, _)
+            | QueryRestriction::Equals(property, _)
+            | QueryRestriction::Regex(property, _) => {
+                self.text.get(property).copied().unwrap_or(self.other)
+            }
+            _ => self.other,
+        }
+    }
+
+    /// Adds up the weights of the `restrictions` the `card` matches. The operands of groups, `OR`s and `XOR`s are scored one by one, so a card that matches a heavier side of an `OR` scores higher.
+    fn score<'a, C, T, I>(
+        &self,
+        card: &C,
+        restrictions: &[QueryRestriction],
+        pool: &I,
+        cache: &Cache<&'a T>,
+    ) -> f64
+    where
+        C: Read,
+        T: Read + 'a,
+        &'a T: Read,
+        I: IntoIterator<Item = &'a T> + Clone,
+    {
+        restrictions
+            .iter()
+            .map(|restriction| match restriction {
+                QueryRestriction::Group(query) => {
+                    self.score(card, &query.restrictions, pool, cache)
+                }
+                QueryRestriction::Or(a, b) | QueryRestriction::Xor(a, b) => {
+                    self.score(card, &a.restrictions, pool, cache)
+                        + self.score(card, &b.restrictions, pool, cache)
+                }
+                restriction => {
+                    let query = Query {
+                        name: String::new(),
+                        restrictions: vec![restriction.clone()],
+                        sort: Sort::None,
+                    };
+                    if matches_query(card, &query, pool, cache) == Ternary::True {
+                        self.weight(restriction)
+                    } else {
+                        0.0
+                    }
+                }
+            })
+            .sum()
+    }
+}
+
+/// Does the same as `search`, but sorts the matched cards by how relevant their matches are, according to the `weights`. Cards with the same relevance keep the order the query's sort gives them.
+///
+/// Unlike fuzzy sorting, this doesn't look at how similar the text is, only at which restrictions matched.
+#[must_use]
+pub fn search_by_relevance<'a, 'b, C, I>(
+    query: &Query,
+    cards: I,
+    weights: &RelevanceWeights,
+) -> Vec<&'a C>
+where
+    C: Read + 'a,
+    I: IntoIterator<Item = &'a C> + Clone + 'b,
+    &'a C: Read,
+{
+    let pool = cards.clone();
+    let cache = Cache::new(HashMap::new());
+    let mut results: Vec<(&C, f64)> = search(query, cards)
+        .into_iter()
+        .map(|card| {
+            let score = weights.score(card, &query.restrictions, &pool, &cache);
+            (card, score)
+        })
+        .collect();
+    results.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+    results.into_iter().map(|(card, _)| card).collect()
+}
+
+/// Parses the `search` of a `RichElement::CardSearch` and returns the cards in the `pool` that it matches, so that a renderer can show them next to the link.
+///
+/// # Errors
+/// When the `search` can't be parsed.
+pub fn resolve_embedded_search<'a>(
+    search: &str,
+    pool: &'a [Card],
+) -> Result<Vec<&'a Card>, Errors> {
+    let query = query_parser::query_parser(search)?;
+    Ok(self::search(&query, pool.iter()))
+}
+
+/// Returns the cards in the `pool` that match any of the `ids`, in the order of the `pool`.
+///
+/// Each identity is turned into a query once, and each card stops being checked at the first identity it matches, so long lists of identities don't need a deep tree of `Or`s.
+#[must_use]
+pub fn match_any_identity<'a>(ids: &[CardId], pool: &'a [Card]) -> Vec<&'a Card> {
+    let queries: Vec<Query> = ids
+        .iter()
+        .map(|id| Query::from_restrictions(id.get_as_query()))
+        .collect();
+    let cache = Cache::default();
+    pool.iter()
+        .filter(|card| {
+            queries
+                .iter()
+                .any(|query| matches_query(*card, query, &pool.iter(), &cache) == Ternary::True)
+        })
+        .collect()
+}
+
+/// Parses `query`, searches the cards in the `cards` JSON array, and returns the matched cards as a JSON array.
+///
+/// Meant for callers that can only exchange strings, like WASM bindings. Nothing here panics; errors are returned as messages instead.
+///
+/// # Errors
+/// When the query can't be parsed, `cards` isn't a valid JSON array of cards, or the results can't be serialized.
+pub fn search_json(query: &str, cards: &str) -> Result<String, String> {
+    let query =
+        query_parser::query_parser(query).map_err(|err| format!("invalid query: {err:?}"))?;
+    let cards: Vec<Card> =
+        serde_json::from_str(cards).map_err(|err| format!("invalid cards: {err}"))?;
+    let results: Vec<&Card> = search(&query, cards.iter());
+    serde_json::to_string(&results).map_err(|err| format!("couldn't serialize results: {err}"))
+}
+
+/// This function checks whether a `card` matches a specific `query`'s restrictions.
+///
+/// Since `devouredby` queries always require two searches, the results of the first search are stored in a `cache` that is internally mutable. This cache is only ever mutated the first time a devouredby query is executed.
+///
 /// The sum total of available `cards` is passed in order to perform searches. This function clones these cards, so this value should be an Iterator.
 #[allow(clippy::too_many_lines)]
 pub fn matches_query<'a, 'b, C, T, I>(
@@ -317,7 +1088,7 @@ pub fn matches_query<'a, 'b, C, T, I>(
 ) -> Ternary
 where
     C: Read,
-    T: Read + 'a + Clone,
+    T: Read + 'a,
     &'a T: Read,
     I: IntoIterator<Item = &'a T> + Clone,
 {
@@ -361,6 +1132,16 @@ where
             QueryRestriction::Comparison(field, comparison) => {
                 filtered = filtered.and(comparison.compare(&card.get_num_property(field)));
             }
+            QueryRestriction::Within(field, other, tolerance) => {
+                let matches = match (card.get_num_property(field), card.get_num_property(other)) {
+                    (Some(a), Some(b)) => (a.assume().abs_diff(b.assume()) <= *tolerance).into(),
+                    _ => Ternary::Void,
+                };
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::FieldComparison(field, op, other) => {
+                filtered = filtered.and(compare_fields(card, *field, *op, *other));
+            }
             QueryRestriction::Contains(field, contains) => {
                 let matches = card
                     .get_text_property(field)
@@ -373,18 +1154,238 @@ where
                     });
                 filtered = filtered.and(matches);
             }
+            QueryRestriction::Equals(field, text) => {
+                let matches = card
+                    .get_text_property(field)
+                    .map_or(Ternary::Void, |property| {
+                        (clean_ascii(&property) == clean_ascii(text)).into()
+                    });
+                filtered = filtered.and(matches);
+            }
             QueryRestriction::Has(field, thing) => {
                 let matches = match_in_vec(card.get_vec_property(field), |text| {
-                    text.to_lowercase().contains(&thing.to_lowercase())
+                    clean_ascii(text).contains(&clean_ascii(thing))
                 });
                 filtered = filtered.and(matches);
             }
-            QueryRestriction::HasKw(thing) => {
-                let matches = match_in_vec(card.get_keywords(), |keyword| {
-                    keyword.name.to_lowercase().contains(&thing.to_lowercase())
+            QueryRestriction::HasExact(field, thing) => {
+                let thing = clean_ascii(thing);
+                let matches = match_in_vec(card.get_vec_property(field), |text| {
+                    clean_ascii(text) == thing
                 });
                 filtered = filtered.and(matches);
             }
+            QueryRestriction::HasRegex(field, regex) => {
+                let matches = match_in_vec(card.get_vec_property(field), |text| {
+                    regex.is_match(&text.to_lowercase())
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasKwExact(name) => {
+                let name = clean_ascii(name);
+                let matches = match_in_vec(card.get_keywords(), |keyword| {
+                    clean_ascii(&keyword.name) == name
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasKw(thing) => {
+                let matches = match_in_vec(card.get_keywords(), |keyword| {
+                    keyword.name.to_lowercase().contains(&thing.to_lowercase())
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasKwData(thing) => {
+                let matches = match_in_vec(card.get_keywords(), |keyword| match &keyword.data {
+                    Some(KeywordData::String(data)) => {
+                        clean_ascii(data).contains(&clean_ascii(thing))
+                    }
+                    _ => false,
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasKwWithData(name, thing) => {
+                let name = clean_ascii(name);
+                let thing = clean_ascii(thing);
+                let matches = match_in_vec(card.get_keywords(), |keyword| {
+                    clean_ascii(&keyword.name) == name
+                        && match &keyword.data {
+                            Some(KeywordData::String(data)) => clean_ascii(data) == thing,
+                            Some(KeywordData::CardId(card_id)) => card_id
+                                .name
+                                .as_ref()
+                                .is_some_and(|data| clean_ascii(data) == thing),
+                            None => false,
+                        }
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::KwSet(names) => {
+                let names: HashSet<String> = names.iter().map(|name| clean_ascii(name)).collect();
+                let matches = card.get_keywords().map_or(Ternary::Void, |keywords| {
+                    let keywords: HashSet<String> = keywords
+                        .iter()
+                        .map(|keyword| clean_ascii(&keyword.name))
+                        .collect();
+                    (keywords == names).into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::KwNumber(name, comparison) => {
+                let name = clean_ascii(name);
+                let matches = card
+                    .get_keywords()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|keyword| clean_ascii(&keyword.name) == name)
+                    .filter_map(|keyword| match &keyword.data {
+                        Some(KeywordData::String(data)) => data.trim().parse::<usize>().ok(),
+                        _ => None,
+                    })
+                    .fold(Ternary::Void, |matches, number| {
+                        matches.or(comparison.compare(&number))
+                    });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasKwDataRegex(regex) => {
+                let matches = match_in_vec(card.get_keywords(), |keyword| match &keyword.data {
+                    Some(KeywordData::String(data)) => regex.is_match(&data.to_lowercase()),
+                    _ => false,
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasArtist => {
+                let matches = card.get_images().map_or(Ternary::Void, |images| {
+                    images.iter().any(|image| !image.authors.is_empty()).into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasImpreciseStat => {
+                let matches = [Number::Cost, Number::Health, Number::Defense, Number::Power]
+                    .iter()
+                    .filter_map(|property| card.get_num_property(property))
+                    .any(|value| !matches!(value, MaybeImprecise::Precise(MaybeVar::Const(_))));
+                filtered = filtered.and(matches.into());
+            }
+            QueryRestriction::DescriptionWords(comparison) => {
+                let words = card.get_description().map(RichString::word_count);
+                filtered = filtered.and(comparison.compare(&words));
+            }
+            QueryRestriction::IsVariable(property) => {
+                let matches = card
+                    .get_num_property(property)
+                    .map_or(Ternary::Void, |value| {
+                        matches!(
+                            value,
+                            MaybeImprecise::Precise(MaybeVar::Var(_) | MaybeVar::VarPlus(..))
+                        )
+                        .into()
+                    });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::Count(property, comparison) => {
+                let matches = card
+                    .get_vec_property(property)
+                    .map_or(Ternary::Void, |elements| {
+                        comparison.compare(&elements.len())
+                    });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::IsReprint => {
+                let key = REPRINTS_KEY.to_owned();
+                if !RefCell::borrow(cache).contains_key(&key) {
+                    let reprints = reprinted_cards(cards.clone());
+                    cache.borrow_mut().insert(key.clone(), reprints);
+                }
+                let matches = card.get_name().map_or(Ternary::Void, |name| {
+                    RefCell::borrow(cache)
+                        .get(&key)
+                        .is_some_and(|reprints| {
+                            reprints
+                                .iter()
+                                .any(|reprint| reprint.get_name() == Some(name))
+                        })
+                        .into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::IsAggressive => {
+                filtered = filtered.and(compare_fields(
+                    card,
+                    Number::Power,
+                    ComparisonOp::GreaterThan,
+                    Number::Health,
+                ));
+            }
+            QueryRestriction::IsDefensive => {
+                filtered = filtered.and(compare_fields(
+                    card,
+                    Number::Power,
+                    ComparisonOp::LowerThan,
+                    Number::Health,
+                ));
+            }
+            QueryRestriction::MissingFlavorText => {
+                let matches = card
+                    .get_flavor_text()
+                    .map_or(Ternary::Void, |text| text.is_empty().into());
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasNumProperty(property) => {
+                filtered = filtered.and(card.get_num_property(property).is_some().into());
+            }
+            QueryRestriction::HasTextProperty(property) => {
+                filtered = filtered.and(card.get_text_property(property).is_some().into());
+            }
+            QueryRestriction::HasArrayProperty(property) => {
+                filtered = filtered.and(card.get_vec_property(property).is_some().into());
+            }
+            QueryRestriction::IdIn(ids) => {
+                let matches = card
+                    .get_text_property(&Text::Id)
+                    .map_or(Ternary::Void, |id| ids.contains(&id).into());
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::HasStatus(status) => {
+                let status = clean_ascii(status);
+                let matches = card.get_legality().map_or(Ternary::Void, |legality| {
+                    legality
+                        .values()
+                        .any(|value| clean_ascii(value) == status)
+                        .into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::LegalIn(format) => {
+                let format = clean_ascii(format);
+                let matches = card.get_legality().map_or(Ternary::Void, |legality| {
+                    legality
+                        .iter()
+                        .any(|(key, value)| {
+                            clean_ascii(key) == format && clean_ascii(value) == "legal"
+                        })
+                        .into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::LinksTo(id) => {
+                let matches = card.get_description().map_or(Ternary::Void, |description| {
+                    description
+                        .iter_all()
+                        .any(|element| match element {
+                            RichElement::SpecificCard { id: linked, .. } => linked == id,
+                            RichElement::CardId { identity, .. } => {
+                                identity.name.as_ref() == Some(id)
+                            }
+                            _ => false,
+                        })
+                        .into()
+                });
+                filtered = filtered.and(matches);
+            }
+            QueryRestriction::DevourChainKins(comparison) => {
+                let kins = devours::devour_chain_kins_cached(card, cards, cache);
+                filtered = filtered.and(comparison.compare(&kins.len()));
+            }
             QueryRestriction::Not(queryres) => {
                 filtered = filtered.and(!matches_query(card, queryres, cards, cache));
             }
@@ -412,76 +1413,109 @@ where
                 filtered = filtered.and(matches);
             }
             QueryRestriction::DevouredBy(devoured_by) => {
-                let key = format!("{devoured_by}");
-                let devoured_cards;
-                let maybe_devourees = RefCell::borrow(cache).get(&key).cloned();
-                if let Some(value) = maybe_devourees {
-                    devoured_cards = value;
-                } else {
-                    let cloned_cards = cards.clone();
-                    let devourers: Vec<&T> = cards
-                        .clone()
-                        .into_iter()
-                        .filter(|card| {
-                            matches_query(card, devoured_by, &cloned_cards, cache) == Ternary::True
-                        })
-                        .collect();
+                let key = devoured_by_key(devoured_by);
+                filtered = filtered.and(is_devoured_by(card, devoured_by, &key, cards, cache));
+            }
+        }
+    }
+    filtered
+}
 
-                    let mut queries: Vec<Query> = vec![];
-
-                    for devourer in devourers {
-                        if let Some(Keyword {
-                            name: _,
-                            data: Some(KeywordData::CardId(card_id)),
-                        }) = devourer
-                            .get_keywords()
-                            .and_then(|x| x.iter().find(|x| x.name == "devours"))
-                        {
-                            queries.push(Query {
-                                name: String::new(),
-                                restrictions: card_id.get_as_query(),
-                                sort: Sort::None,
-                            });
-                        }
-                    }
+/// The key under which the cards devoured by the cards that match `devoured_by` are cached.
+///
+/// This uses the `Debug` form of the query, which quotes and escapes text and names every restriction, so different queries can't share a key. The `Display` form can't be used, because it doesn't quote everything, like the ids in `ids:[a,b]`.
+fn devoured_by_key(devoured_by: &Query) -> String {
+    format!("devouredby:{devoured_by:?}")
+}
 
-                    let devourees_query = queries
-                        .into_iter()
-                        .reduce(|first, second| Query {
-                            name: String::new(),
-                            restrictions: vec![QueryRestriction::Or(first, second)],
-                            sort: Sort::None,
-                        })
-                        .unwrap_or(Query {
-                            name: String::new(),
-                            restrictions: vec![],
-                            sort: Sort::None,
-                        });
-
-                    let devourees_query = Query {
-                        name: query.name.clone(),
-                        restrictions: devourees_query.restrictions,
-                        sort: query.sort,
-                    };
+/// Checks whether the `card` is devoured by any of the `cards` that match `devoured_by`. The devoured cards are found once and then kept in the `cache` under `key`.
+fn is_devoured_by<'a, C, T, I>(
+    card: &C,
+    devoured_by: &Query,
+    key: &str,
+    cards: &I,
+    cache: &Cache<&'a T>,
+) -> Ternary
+where
+    C: Read,
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T> + Clone,
+{
+    let is_devoured = |devoured_cards: &[&'a T]| {
+        devoured_cards
+            .iter()
+            .any(|x| x.get_name() == card.get_name())
+    };
+    let cached = RefCell::borrow(cache)
+        .get(key)
+        .map(|devoured_cards| is_devoured(devoured_cards));
+    let matches = cached.unwrap_or_else(|| {
+        let cloned_cards = cards.clone();
+        let devourers: Vec<&T> = cards
+            .clone()
+            .into_iter()
+            .filter(|card| matches_query(card, devoured_by, &cloned_cards, cache) == Ternary::True)
+            .collect();
 
-                    devoured_cards = search(&devourees_query, cloned_cards);
-                    cache.borrow_mut().insert(key, devoured_cards.clone());
-                }
-                if devoured_cards
-                    .iter()
-                    .any(|x| x.get_name() == card.get_name())
-                {
-                    filtered = filtered.and(Ternary::True);
-                } else {
-                    filtered = filtered.and(Ternary::False);
-                }
+        let mut queries: Vec<Query> = vec![];
+
+        for devourer in devourers {
+            if let Some(Keyword {
+                name: _,
+                data: Some(KeywordData::CardId(card_id)),
+            }) = devourer
+                .get_keywords()
+                .and_then(|x| x.iter().find(|x| x.name == "devours"))
+            {
+                queries.push(Query::from_restrictions(card_id.get_as_query()));
             }
         }
+
+        let devourees_query = queries
+            .into_iter()
+            .reduce(|first, second| {
+                Query::from_restrictions(vec![QueryRestriction::Or(first, second)])
+            })
+            .unwrap_or_else(|| Query::from_restrictions(vec![]));
+
+        let devoured_cards = search(&devourees_query, cloned_cards);
+        let matches = is_devoured(&devoured_cards);
+        cache.borrow_mut().insert(key.to_owned(), devoured_cards);
+        matches
+    });
+    matches.into()
+}
+
+/// The key `IsReprint` keeps the result of `reprinted_cards` under in the cache.
+const REPRINTS_KEY: &str = "is:reprint";
+
+/// Returns the `cards` whose name is shared by a card with a different id.
+fn reprinted_cards<'a, T, I>(cards: I) -> Vec<&'a T>
+where
+    T: Read + 'a,
+    I: IntoIterator<Item = &'a T>,
+{
+    let cards: Vec<&T> = cards.into_iter().collect();
+    let mut ids: HashMap<&str, HashSet<String>> = HashMap::new();
+    for card in &cards {
+        if let (Some(name), Some(id)) = (card.get_name(), card.get_text_property(&Text::Id)) {
+            ids.entry(name).or_default().insert(id);
+        }
     }
-    filtered
+    cards
+        .into_iter()
+        .filter(|card| {
+            card.get_name()
+                .and_then(|name| ids.get(name))
+                .is_some_and(|ids| ids.len() > 1)
+        })
+        .collect()
 }
 
 /// Returns whether any part of an optional `vec` fulfills a `cond`ition.
+///
+/// An empty `vec` gives `False`, as it is known to have nothing that fulfills the condition. Only a missing `vec`, like the unspecified arrays of a `CardId`, gives `Void`. This way, `Card`s and `CardId`s that list the same things match the same way.
 pub fn match_in_vec<T>(vec: Option<&[T]>, cond: impl Fn(&T) -> bool) -> Ternary {
     vec.map_or(Ternary::Void, |vec| {
         if vec.iter().any(cond) {
@@ -491,3 +1525,2029 @@ pub fn match_in_vec<T>(vec: Option<&[T]>, cond: impl Fn(&T) -> bool) -> Ternary
         }
     })
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use regex::Regex;
+
+    use crate::{
+        cards::{
+            properties::{Array, Number, Text},
+            rich_text::{RichElement, RichString},
+            Card, CardId, Image, ImageSource, Keyword, KeywordData,
+        },
+        numbers::{Comparison, ImpreciseOrd, MaybeImprecise, MaybeVar},
+    };
+
+    use super::{
+        devoured_by_key, fuzzy,
+        fuzzy::{
+            weighted_compare, weighted_compare_with, weighted_compare_with_synonyms, FuzzyScope,
+            FuzzyWeights, SynonymTable,
+        },
+        fuzzy_scoped, fuzzy_with_synonyms, labels_for_card, match_any_identity, matches_query,
+        query_parser::{query_parser, query_parser_with_options, ParseOptions},
+        resolve_embedded_search, search, search_by_relevance, search_json, search_scored,
+        search_sorted_by, search_with_facets, Cache, ComparisonOp, Errors, Ordering, Query,
+        QueryRestriction, RelevanceWeights, Sort, Ternary,
+    };
+
+    #[test]
+    fn test_keyword_data_regex() {
+        let armored = Card {
+            name: "armored".to_string(),
+            keywords: vec![Keyword {
+                name: "armor".to_string(),
+                data: Some(KeywordData::String("3".to_string())),
+            }],
+            ..Default::default()
+        };
+        let plain = Card {
+            name: "plain".to_string(),
+            keywords: vec![Keyword {
+                name: "armor".to_string(),
+                data: None,
+            }],
+            ..Default::default()
+        };
+        let cards = [armored, plain];
+
+        let query = query_parser(r"kwdata:/\d+/").expect("couldn't parse query");
+        let result = search(&query, cards.iter());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "armored");
+
+        let id = CardId {
+            name: None,
+            cost: None,
+            description: None,
+            keywords: None,
+            r#type: None,
+            kins: None,
+            health: None,
+            defense: None,
+            power: None,
+            abilities: None,
+            functions: None,
+        };
+        let cache = Cache::default();
+        assert_eq!(
+            matches_query(&id, &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+
+        // A card's keyword list is always there, so having no keywords is False rather than Void.
+        let keywordless = Card::default();
+        assert_eq!(
+            matches_query(&keywordless, &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+    }
+
+    #[test]
+    fn test_type_facets() {
+        let cards = [
+            Card {
+                name: "a".to_string(),
+                r#type: "creature".to_string(),
+                cost: MaybeImprecise::Precise(MaybeVar::Const(1)),
+                ..Default::default()
+            },
+            Card {
+                name: "b".to_string(),
+                r#type: "creature".to_string(),
+                cost: MaybeImprecise::Precise(MaybeVar::Const(2)),
+                ..Default::default()
+            },
+            Card {
+                name: "c".to_string(),
+                r#type: "command".to_string(),
+                cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+                ..Default::default()
+            },
+            Card {
+                name: "d".to_string(),
+                r#type: "command".to_string(),
+                cost: MaybeImprecise::Precise(MaybeVar::Const(9)),
+                ..Default::default()
+            },
+        ];
+
+        let query = query_parser("c<5").expect("couldn't parse query");
+        let (result, facets) = search_with_facets(&query, cards.iter(), &[Text::Type]);
+        assert_eq!(result.len(), 3);
+        let types = &facets[&Text::Type];
+        assert_eq!(types["creature"], 2);
+        assert_eq!(types["command"], 1);
+        assert_eq!(types.len(), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_synonyms() {
+        let card = Card {
+            name: "Striker".to_string(),
+            description: RichString {
+                elements: vec![RichElement::String(
+                    "Deal 2 damage to a creature.".to_string(),
+                )],
+            },
+            ..Default::default()
+        };
+        let mut synonyms = SynonymTable::new();
+        synonyms.insert("dmg", "damage");
+
+        assert!(!super::fuzzy(&card, "dmg"));
+        assert!(fuzzy_with_synonyms(&card, "dmg", &synonyms));
+        assert!(fuzzy_with_synonyms(&card, "2 dmg", &synonyms));
+        assert!(!fuzzy_with_synonyms(&card, "dmg", &SynonymTable::new()));
+        assert!(
+            weighted_compare_with_synonyms(&card, "dmg", &synonyms)
+                > weighted_compare(&card, "dmg")
+        );
+
+        let options = ParseOptions {
+            synonyms: Some(&synonyms),
+            ..Default::default()
+        };
+        let cards = [card];
+        for query in ["dmg", "2 dmg", "damage"] {
+            let query = query_parser_with_options(query, &options).expect("couldn't parse query");
+            assert_eq!(search(&query, cards.iter()).len(), 1);
+        }
+        let query = query_parser("dmg").expect("couldn't parse query");
+        assert!(search(&query, cards.iter()).is_empty());
+    }
+
+    #[test]
+    fn test_is_credited() {
+        let credited = Card {
+            name: "credited".to_string(),
+            images: vec![Image {
+                sources: ImageSource::CardName,
+                authors: vec!["Arity".to_string()],
+            }],
+            ..Default::default()
+        };
+        let uncredited = Card {
+            name: "uncredited".to_string(),
+            images: vec![Image {
+                sources: ImageSource::CardName,
+                authors: vec![],
+            }],
+            ..Default::default()
+        };
+        let cards = [credited, uncredited];
+
+        let query = query_parser("is:credited").expect("couldn't parse query");
+        let result = search(&query, cards.iter());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "credited");
+
+        let query = query_parser("-is:credited").expect("couldn't parse query");
+        let result = search(&query, cards.iter());
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].name, "uncredited");
+
+        let id = CardId {
+            name: None,
+            cost: None,
+            description: None,
+            keywords: None,
+            r#type: None,
+            kins: None,
+            health: None,
+            defense: None,
+            power: None,
+            abilities: None,
+            functions: None,
+        };
+        let query = query_parser("is:credited").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&id, &query, &cards.iter(), &Cache::default()),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_within() {
+        let even = Card {
+            name: "even".to_string(),
+            r#type: "creature".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            power: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..Default::default()
+        };
+        let strong = Card {
+            name: "strong".to_string(),
+            r#type: "creature".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            power: MaybeImprecise::Precise(MaybeVar::Const(5)),
+            ..Default::default()
+        };
+        let command = Card {
+            name: "command".to_string(),
+            r#type: "command".to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..Default::default()
+        };
+        let cards = [even, strong, command];
+        let query = query_parser("power~cost±1").expect("couldn't parse query");
+        let cache = Cache::default();
+
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::False, Ternary::Void]);
+
+        let query = query_parser("p~c+-2").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&cards[1], &query, &cards.iter(), &cache),
+            Ternary::True
+        );
+    }
+
+    #[test]
+    fn test_parent_kin_matches_children() {
+        let card = |name: &str, kins: &[&str]| Card {
+            name: name.to_string(),
+            kins: kins.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("ant", &["ant", "insect"]),
+            card("bee", &["bee", "insect"]),
+            card("insect", &["insect"]),
+            card("reptile", &["reptile"]),
+        ];
+
+        let query = query_parser("kin:insect").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(result, ["ant", "bee", "insect"]);
+    }
+
+    #[test]
+    fn test_query_serde_round_trip() {
+        let query = query_parser("(c>2 OR kw:flying) -n:/queen/ devours:(k:ant) so:cost")
+            .expect("couldn't parse query");
+
+        let yaml = serde_yaml::to_string(&query).expect("couldn't serialize query to yaml");
+        assert!(yaml.contains("kind: Or"));
+        let from_yaml: Query = serde_yaml::from_str(&yaml).expect("couldn't read query yaml");
+        assert_eq!(from_yaml.to_string(), query.to_string());
+        assert_eq!(
+            serde_yaml::to_string(&from_yaml).expect("couldn't serialize query to yaml"),
+            yaml
+        );
+
+        let json = serde_json::to_string(&query).expect("couldn't serialize query to json");
+        let from_json: Query = serde_json::from_str(&json).expect("couldn't read query json");
+        assert_eq!(from_json.to_string(), query.to_string());
+    }
+
+    #[test]
+    fn test_canonicalize() {
+        let canonical = |query: &str| {
+            query_parser(query)
+                .expect("couldn't parse query")
+                .canonicalize()
+        };
+
+        let same = [
+            ("c>2 c>=1 (p<5 k:ant)", "k:ant p<=4 c>=3"),
+            ("-(-(c>2)) kw:flying", "kw:flying c>=3"),
+            ("(c>2 OR kw:flying)", "(kw:flying OR c>=3)"),
+            ("h<3 h<=4 h<=1 k:ant k:ant", "k:ant h<2"),
+            ("n:/^a/ devours:(c>1 c>0)", "devours:(c>=2) n:/^a/"),
+        ];
+        for (a, b) in same {
+            assert_eq!(canonical(a), canonical(b), "{a} and {b}");
+        }
+
+        let different = [
+            ("c>2", "c>=2"),
+            ("c>2", "-c>2"),
+            ("c>2 p<3", "c<3 p>2"),
+            ("(c>2 OR kw:flying)", "c>2 kw:flying"),
+            ("(c>2 XOR kw:flying)", "(c>2 OR kw:flying)"),
+            ("--c>2", "c>2"),
+        ];
+        for (a, b) in different {
+            assert_ne!(canonical(a), canonical(b), "{a} and {b}");
+        }
+    }
+
+    #[test]
+    fn test_search_json() {
+        let cards = r#"[
+            {"id": "ant", "name": "Ant", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {}},
+            {"id": "bee", "name": "Bee", "description": "", "cost": 3, "type": "creature", "health": 1, "defense": 0, "power": 2, "legality": {}}
+        ]"#;
+
+        let results = search_json("c>2", cards).expect("couldn't search");
+        let results: Vec<Card> = serde_json::from_str(&results).expect("results aren't cards");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "Bee");
+
+        assert!(search_json("c>2 (", cards).is_err());
+        assert!(search_json("c>2", "[{}]").is_err());
+    }
+
+    #[test]
+    fn test_kin_casing_and_accents() {
+        let card = |name: &str, kins: &[&str]| Card {
+            name: name.to_string(),
+            kins: kins.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("cultist", &["cult of nä"]),
+            card("acolyte", &["Cult of Na"]),
+            card("ant", &["ant", "insect"]),
+        ];
+
+        for kin in [
+            "\"cult of nä\"",
+            "\"Cult of Nä\"",
+            "\"cult of na\"",
+            "\"CULT OF NA\"",
+            "\"CULT OF NÄ\"",
+        ] {
+            let query = query_parser(&format!("k:{kin}")).expect("couldn't parse query");
+            let result: Vec<&str> = search(&query, cards.iter())
+                .iter()
+                .map(|card| card.name.as_str())
+                .collect();
+            assert_eq!(result, ["acolyte", "cultist"], "{kin}");
+        }
+    }
+
+    #[test]
+    fn test_links_to() {
+        let card = |name: &str, elements: Vec<RichElement>| Card {
+            name: name.to_string(),
+            description: RichString { elements },
+            ..Default::default()
+        };
+        let cards = [
+            card(
+                "herald",
+                vec![RichElement::Saga(vec![RichString {
+                    elements: vec![RichElement::SpecificCard {
+                        display: "the Green Queen".to_string(),
+                        id: "green-queen-001".to_string(),
+                    }],
+                }])],
+            ),
+            card(
+                "courtier",
+                vec![RichElement::CardId {
+                    display: "a queen".to_string(),
+                    identity: CardId::builder().name("green-queen-001").build(),
+                }],
+            ),
+            card(
+                "pretender",
+                vec![RichElement::SpecificCard {
+                    display: "the Red Queen".to_string(),
+                    id: "red-queen-001".to_string(),
+                }],
+            ),
+            card(
+                "peasant",
+                vec![RichElement::String("green-queen-001".to_string())],
+            ),
+        ];
+
+        let query = query_parser("links:green-queen-001").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(result, ["courtier", "herald"]);
+
+        let cache = Cache::default();
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_labels_for_card() {
+        let card = |name: &str, cost: usize, kins: &[&str]| Card {
+            name: name.to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(cost)),
+            kins: kins.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("ant", 1, &["ant", "insect"]),
+            card("toad", 4, &["amphibian"]),
+        ];
+
+        let cheap = query_parser("c<=2").expect("couldn't parse query");
+        let insects = query_parser("k:insect").expect("couldn't parse query");
+        let amphibians = query_parser("k:amphibian").expect("couldn't parse query");
+        let labeled = [
+            ("cheap", &cheap),
+            ("insects", &insects),
+            ("amphibians", &amphibians),
+        ];
+
+        assert_eq!(
+            labels_for_card(&cards[0], &labeled, &cards.iter()),
+            ["cheap", "insects"]
+        );
+        assert_eq!(
+            labels_for_card(&cards[1], &labeled, &cards.iter()),
+            ["amphibians"]
+        );
+    }
+
+    #[test]
+    fn test_sort_reversed() {
+        assert_eq!(Ordering::Ascending.reverse(), Ordering::Descending);
+        assert_eq!(Ordering::Descending.reverse(), Ordering::Ascending);
+
+        assert_eq!(Sort::None.reversed(), Sort::None);
+        assert_eq!(Sort::Fuzzy.reversed(), Sort::Fuzzy);
+        assert_eq!(
+            Sort::Alphabet(Text::Name, Ordering::Ascending).reversed(),
+            Sort::Alphabet(Text::Name, Ordering::Descending)
+        );
+        assert_eq!(
+            Sort::Numeric(Number::Cost, Ordering::Descending).reversed(),
+            Sort::Numeric(Number::Cost, Ordering::Ascending)
+        );
+        assert_eq!(
+            Sort::Numeric(Number::Power, Ordering::Ascending)
+                .reversed()
+                .reversed(),
+            Sort::Numeric(Number::Power, Ordering::Ascending)
+        );
+    }
+
+    #[test]
+    fn test_is_imprecise() {
+        let card = |name: &str, power: MaybeImprecise| Card {
+            name: name.to_string(),
+            r#type: "creature".to_string(),
+            power,
+            ..Default::default()
+        };
+        let cards = [
+            card("constant", MaybeImprecise::Precise(MaybeVar::Const(2))),
+            card("variable", MaybeImprecise::Precise(MaybeVar::Var('X'))),
+            card(
+                "imprecise",
+                MaybeImprecise::Imprecise(Comparison::GreaterThan(1)),
+            ),
+        ];
+        let query = query_parser("is:imprecise").expect("couldn't parse query");
+        let cache = Cache::default();
+
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::False, Ternary::True, Ternary::True]);
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+    }
+
+    #[test]
+    fn test_ids_in() {
+        let card = |id: &str| Card {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("ant-001"), card("bee-001"), card("wasp-001")];
+
+        let query = query_parser("ids:[ant-001,wasp-001,moth-001]").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.id.as_str())
+            .collect();
+        assert_eq!(result, ["ant-001", "wasp-001"]);
+
+        let query = query_parser("ids:bee-001").expect("couldn't parse query");
+        assert_eq!(search(&query, cards.iter()).len(), 1);
+
+        let cache = Cache::default();
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_all_words() {
+        let card = |name: &str, description: &str| Card {
+            name: name.to_string(),
+            description: RichString {
+                elements: vec![RichElement::String(description.to_string())],
+            },
+            ..Default::default()
+        };
+        let cards = [
+            card("Royal Decree", "The queen is green with envy."),
+            card("Green Queen", ""),
+            card("Red Queen", "Green things wither."),
+            card("Gardener", "Plant a green seed."),
+        ];
+        let options = ParseOptions {
+            fuzzy_all_words: true,
+            ..Default::default()
+        };
+
+        let query =
+            query_parser_with_options("green queen", &options).expect("couldn't parse query");
+        assert_eq!(query.name, "green queen");
+        let mut result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        result.sort_unstable();
+        assert_eq!(result, ["Green Queen", "Red Queen", "Royal Decree"]);
+
+        let query = query_parser("green queen").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(result, ["Green Queen"]);
+    }
+
+    #[test]
+    fn test_description_words() {
+        let card = |name: &str, description: &str| Card {
+            name: name.to_string(),
+            description: RichString {
+                elements: vec![RichElement::String(description.to_string())],
+            },
+            ..Default::default()
+        };
+        let cards = [
+            card("terse", "Draw a card."),
+            card(
+                "wordy",
+                "Draw a card, then discard a card, then shuffle your deck.",
+            ),
+            Card {
+                name: "blank".to_string(),
+                ..Default::default()
+            },
+        ];
+        let names = |query: &str| -> Vec<String> {
+            let query = query_parser(query).expect("couldn't parse query");
+            search(&query, cards.iter())
+                .iter()
+                .map(|card| card.name.clone())
+                .collect()
+        };
+
+        assert_eq!(names("desclen:>5"), ["wordy"]);
+        assert_eq!(names("desclen<=3"), ["blank", "terse"]);
+        assert_eq!(names("desclen=0"), ["blank"]);
+    }
+
+    #[test]
+    fn test_is_reprint() {
+        let card = |id: &str, name: &str| Card {
+            id: id.to_string(),
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [
+            card("ant-001", "Ant"),
+            card("ant-002", "Ant"),
+            card("bee-001", "Bee"),
+        ];
+        let query = query_parser("is:reprint").expect("couldn't parse query");
+        let cache = Cache::default();
+
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::True, Ternary::False]);
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+
+        let query = query_parser("-is:reprint").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.id.as_str())
+            .collect();
+        assert_eq!(result, ["bee-001"]);
+    }
+
+    #[test]
+    fn test_midpoint_sort() {
+        let card = |name: &str, cost: MaybeImprecise| Card {
+            name: name.to_string(),
+            cost,
+            ..Default::default()
+        };
+        let cards = [
+            card(
+                "more than 3",
+                MaybeImprecise::Imprecise(Comparison::GreaterThan(3)),
+            ),
+            card("exactly 4", MaybeImprecise::Imprecise(Comparison::Equal(4))),
+            card(
+                "less than 2",
+                MaybeImprecise::Imprecise(Comparison::LowerThan(2)),
+            ),
+            card("3", MaybeImprecise::Precise(MaybeVar::Const(3))),
+        ];
+        let names = |query: &str| -> Vec<String> {
+            let query = query_parser(query).expect("couldn't parse query");
+            search(&query, cards.iter())
+                .iter()
+                .map(|card| card.name.clone())
+                .collect()
+        };
+
+        assert_eq!(
+            names("som:cost"),
+            ["less than 2", "3", "exactly 4", "more than 3"]
+        );
+        assert_eq!(
+            names("somd:cost"),
+            ["more than 3", "exactly 4", "3", "less than 2"]
+        );
+    }
+
+    #[test]
+    fn test_empty_and_missing_arrays() {
+        let card = Card::default();
+        let empty_id = CardId {
+            functions: Some(vec![]),
+            ..Default::default()
+        };
+        let missing_id = CardId::default();
+        let cards = [card.clone()];
+        let cache = Cache::default();
+
+        let has = query_parser("fn:draw").expect("couldn't parse query");
+        let lacks = query_parser("-fn:draw").expect("couldn't parse query");
+
+        assert_eq!(
+            matches_query(&card, &has, &cards.iter(), &cache),
+            Ternary::False
+        );
+        assert_eq!(
+            matches_query(&card, &lacks, &cards.iter(), &cache),
+            Ternary::True
+        );
+        assert_eq!(
+            matches_query(&empty_id, &has, &cards.iter(), &cache),
+            Ternary::False
+        );
+        assert_eq!(
+            matches_query(&empty_id, &lacks, &cards.iter(), &cache),
+            Ternary::True
+        );
+        assert_eq!(
+            matches_query(&missing_id, &has, &cards.iter(), &cache),
+            Ternary::Void
+        );
+        assert_eq!(
+            matches_query(&missing_id, &lacks, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_missing_flavor_text() {
+        let card = |name: &str, flavor_text: &str| Card {
+            name: name.to_string(),
+            flavor_text: flavor_text.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("Ant", "Small but mighty."), card("Bee", "")];
+        let cache = Cache::default();
+
+        for query in ["missing:flavor", "is:flavorless"] {
+            let query = query_parser(query).expect("couldn't parse query");
+            let results: Vec<Ternary> = cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect();
+            assert_eq!(results, [Ternary::False, Ternary::True]);
+            assert_eq!(
+                matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+                Ternary::Void
+            );
+        }
+        assert!(query_parser("missing:power").is_err());
+    }
+
+    #[test]
+    fn test_keyword_with_data() {
+        let mut cards: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "toad-001", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "green-queen-001"}}]},
+                {"id": "frog-001", "name": "Frog", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "green-queen-002"}}]}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+        cards.push(Card {
+            id: "wasp-001".to_string(),
+            name: "Wasp".to_string(),
+            keywords: vec![Keyword {
+                name: "mark".to_string(),
+                data: Some(KeywordData::String("Green Queen".to_string())),
+            }],
+            ..Default::default()
+        });
+        let cache = Cache::default();
+
+        let query = query_parser("kw:devours=green-queen-001").expect("couldn't parse query");
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::False, Ternary::False]);
+
+        let query = query_parser("kw:\"mark=green queen\"").expect("couldn't parse query");
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::False, Ternary::False, Ternary::True]);
+
+        for search in ["kw=mark", "kw:=MARK"] {
+            let query = query_parser(search).expect("couldn't parse query");
+            assert!(
+                matches!(
+                    query.restrictions.as_slice(),
+                    [QueryRestriction::HasKwExact(name)] if name.eq_ignore_ascii_case("mark")
+                ),
+                "{search}"
+            );
+            let results: Vec<Ternary> = cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect();
+            assert_eq!(results, [Ternary::False, Ternary::False, Ternary::True]);
+        }
+        let query = query_parser("kw=mar").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&cards[2], &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+        assert!(matches!(query_parser("kw="), Err(Errors::EmptyKeywordName)));
+    }
+
+    #[test]
+    fn test_keyword_number() {
+        let card = |name: &str, keywords: Vec<Keyword>| Card {
+            name: name.to_string(),
+            keywords,
+            ..Default::default()
+        };
+        let armor = |data: &str| Keyword {
+            name: "armor".to_string(),
+            data: Some(KeywordData::String(data.to_string())),
+        };
+        let cards = [
+            card("Beetle", vec![armor("3")]),
+            card("Ant", vec![armor("1")]),
+            card("Crab", vec![armor("lots")]),
+            card("Bee", vec![]),
+        ];
+        let query = query_parser("kwnum:armor>=2").expect("couldn't parse query");
+        let cache = Cache::default();
+
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(
+            results,
+            [Ternary::True, Ternary::False, Ternary::Void, Ternary::Void]
+        );
+        assert!(query_parser("kwnum:armor").is_err());
+        assert!(matches!(
+            query_parser("kwnum:>=2"),
+            Err(Errors::EmptyKeywordName)
+        ));
+    }
+
+    #[test]
+    fn test_search_by_relevance() {
+        let cards: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "aphid", "name": "Aphid", "description": "Feeds the bee.", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {}},
+                {"id": "bee", "name": "Bee", "description": "Stings.", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {}},
+                {"id": "moth", "name": "Moth", "description": "Flutters.", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {}}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+        let query = query_parser("n:bee OR desc:bee").expect("couldn't parse query");
+
+        let names = |results: Vec<&Card>| -> Vec<String> {
+            results.iter().map(|card| card.name.clone()).collect()
+        };
+        assert_eq!(names(search(&query, cards.iter())), ["Aphid", "Bee"]);
+        assert_eq!(
+            names(search_by_relevance(
+                &query,
+                cards.iter(),
+                &RelevanceWeights::default()
+            )),
+            ["Bee", "Aphid"]
+        );
+
+        let weights = RelevanceWeights {
+            text: HashMap::from([(Text::Name, 1.0), (Text::Description, 2.0)]),
+            other: 0.0,
+        };
+        assert_eq!(
+            names(search_by_relevance(&query, cards.iter(), &weights)),
+            ["Aphid", "Bee"]
+        );
+    }
+
+    #[test]
+    fn test_any_and_all() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("Ant"), card("Bee"), card("Moth"), card("Wasp")];
+        let named = |name: &str| QueryRestriction::Contains(Text::Name, name.to_string());
+        let matched = |restriction: QueryRestriction| -> Vec<String> {
+            let query = Query {
+                name: String::new(),
+                restrictions: vec![restriction],
+                sort: Sort::None,
+            };
+            search(&query, cards.iter())
+                .iter()
+                .map(|card| card.name.clone())
+                .collect()
+        };
+
+        assert!(matched(QueryRestriction::any(vec![])).is_empty());
+        assert_eq!(matched(QueryRestriction::all(vec![])).len(), 4);
+
+        assert_eq!(matched(QueryRestriction::any(vec![named("bee")])), ["Bee"]);
+        assert_eq!(QueryRestriction::all(vec![named("bee")]), named("bee"));
+
+        assert_eq!(
+            matched(QueryRestriction::any(vec![
+                named("ant"),
+                named("moth"),
+                named("wasp")
+            ])),
+            ["Ant", "Moth", "Wasp"]
+        );
+        assert_eq!(
+            matched(QueryRestriction::all(vec![named("a"), named("s")])),
+            ["Wasp"]
+        );
+    }
+
+    #[test]
+    fn test_alphabetical_sort_ignores_case_and_diaereses() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Zebra"),
+            card("apple"),
+            card("Äpple"),
+            card("banana"),
+            card("Ölm"),
+        ];
+
+        let sorted = |query: &str| -> Vec<String> {
+            search(
+                &query_parser(query).expect("couldn't parse query"),
+                cards.iter(),
+            )
+            .iter()
+            .map(|card| card.name.clone())
+            .collect()
+        };
+        assert_eq!(
+            sorted("so:name"),
+            ["apple", "Äpple", "banana", "Ölm", "Zebra"]
+        );
+        assert_eq!(
+            sorted("sod:name"),
+            ["Zebra", "Ölm", "banana", "Äpple", "apple"]
+        );
+    }
+
+    #[test]
+    fn test_exact_keyword_set() {
+        let card = |name: &str, keywords: &[&str]| Card {
+            name: name.to_string(),
+            keywords: keywords
+                .iter()
+                .map(|name| Keyword {
+                    name: (*name).to_string(),
+                    data: None,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Hawk", &["haste", "Flying"]),
+            card("Wyvern", &["flying", "haste", "trample"]),
+            card("Ant", &[]),
+        ];
+        let cache = Cache::default();
+        let results = |query: &str| -> Vec<Ternary> {
+            let query = query_parser(query).expect("couldn't parse query");
+            cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect()
+        };
+
+        assert_eq!(
+            results("keywords==[flying,haste]"),
+            [Ternary::True, Ternary::False, Ternary::False]
+        );
+        assert_eq!(
+            results("keywords==[flying,haste,trample]"),
+            [Ternary::False, Ternary::True, Ternary::False]
+        );
+        assert_eq!(
+            results("keywords==[]"),
+            [Ternary::False, Ternary::False, Ternary::True]
+        );
+        let query = query_parser("keywords==[flying,haste]").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_resolve_embedded_search() {
+        let card = |name: &str, kin: &str, cost: usize| Card {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            kins: vec![kin.to_string()],
+            cost: MaybeImprecise::Precise(MaybeVar::Const(cost)),
+            ..Default::default()
+        };
+        let pool = [
+            card("Ant", "insect", 1),
+            card("Bee", "insect", 1),
+            card("Beetle", "insect", 3),
+            card("Toad", "amphibian", 1),
+        ];
+        let queen = Card {
+            name: "Queen".to_string(),
+            description: RichString {
+                elements: vec![
+                    RichElement::String("Summon ".to_string()),
+                    RichElement::CardSearch {
+                        display: "a cheap insect".to_string(),
+                        search: "k:insect c<2".to_string(),
+                    },
+                ],
+            },
+            ..Default::default()
+        };
+
+        let search = queen
+            .description
+            .iter_all()
+            .find_map(|element| match element {
+                RichElement::CardSearch { search, .. } => Some(search),
+                _ => None,
+            })
+            .expect("the queen should link to a search");
+        let names: Vec<&str> = resolve_embedded_search(search, &pool)
+            .expect("couldn't resolve search")
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(names, ["Ant", "Bee"]);
+        assert!(resolve_embedded_search("k:(", &pool).is_err());
+    }
+
+    #[test]
+    fn test_kin_exact_and_regex() {
+        let card = |name: &str, kins: &[&str]| Card {
+            name: name.to_string(),
+            kins: kins.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Bee", &["bee", "insect"]),
+            card("Beetle", &["beetle", "insect"]),
+            card("Toad", &["amphibian"]),
+        ];
+        let cache = Cache::default();
+        let results = |query: &str| -> Vec<Ternary> {
+            let query = query_parser(query).expect("couldn't parse query");
+            cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect()
+        };
+
+        assert_eq!(
+            results("k:bee"),
+            [Ternary::True, Ternary::True, Ternary::False]
+        );
+        assert_eq!(
+            results("k=bee"),
+            [Ternary::True, Ternary::False, Ternary::False]
+        );
+        assert_eq!(
+            results("k:/^(bee|amphibian)$/"),
+            [Ternary::True, Ternary::False, Ternary::True]
+        );
+        let query = query_parser("k=bee").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_aggressive_and_defensive() {
+        let card = |name: &str, r#type: &str, power: usize, health: usize| Card {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            power: MaybeImprecise::Precise(MaybeVar::Const(power)),
+            health: MaybeImprecise::Precise(MaybeVar::Const(health)),
+            ..Default::default()
+        };
+        let cards = [
+            card("Wasp", "creature", 4, 2),
+            card("Turtle", "creature", 2, 4),
+            card("Ant", "creature", 2, 2),
+            card("Shuffle", "command", 0, 0),
+        ];
+        let cache = Cache::default();
+        let results = |query: &str| -> Vec<Ternary> {
+            let query = query_parser(query).expect("couldn't parse query");
+            cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect()
+        };
+
+        assert_eq!(
+            results("is:aggressive"),
+            [Ternary::True, Ternary::False, Ternary::False, Ternary::Void]
+        );
+        assert_eq!(
+            results("is:defensive"),
+            [Ternary::False, Ternary::True, Ternary::False, Ternary::Void]
+        );
+
+        // Imprecise stats compare the same way as in `p>h`: a power above 2 beats a health of 3.
+        let moth = Card {
+            name: "Moth".to_string(),
+            r#type: "creature".to_string(),
+            power: MaybeImprecise::Imprecise(Comparison::GreaterThan(2)),
+            health: MaybeImprecise::Precise(MaybeVar::Const(3)),
+            ..Default::default()
+        };
+        for (query, expected) in [
+            ("is:aggressive", Ternary::True),
+            ("is:defensive", Ternary::False),
+        ] {
+            let query = query_parser(query).expect("couldn't parse query");
+            assert_eq!(
+                matches_query(&moth, &query, &cards.iter(), &cache),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_name_equals() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("Green Queen"), card("Green Queen's Guard")];
+        let query = query_parser("name=\"green queen\"").expect("couldn't parse query");
+        let result: Vec<&str> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(result, ["Green Queen"]);
+    }
+
+    #[test]
+    fn test_devoured_by_without_clone() {
+        use crate::{
+            cards::properties::{Array, Read},
+            numbers::MaybeImprecise,
+        };
+
+        /// A card that can't be cloned, so searching it proves that nothing clones cards.
+        struct Unclonable(Card);
+
+        impl Read for Unclonable {
+            fn get_num_property(&self, property: &Number) -> Option<MaybeImprecise> {
+                self.0.get_num_property(property)
+            }
+            fn get_text_property(&self, property: &Text) -> Option<String> {
+                self.0.get_text_property(property)
+            }
+            fn get_vec_property(&self, property: &Array) -> Option<&[String]> {
+                self.0.get_vec_property(property)
+            }
+            fn get_keywords(&self) -> Option<&[Keyword]> {
+                self.0.get_keywords()
+            }
+            fn get_name(&self) -> Option<&str> {
+                self.0.get_name()
+            }
+            fn get_description(&self) -> Option<&RichString> {
+                self.0.get_description()
+            }
+            fn get_type(&self) -> Option<&str> {
+                self.0.get_type()
+            }
+            fn get_kins(&self) -> Option<&[String]> {
+                self.0.get_kins()
+            }
+            fn get_flavor_text(&self) -> Option<&str> {
+                self.0.get_flavor_text()
+            }
+        }
+
+        impl Read for &Unclonable {
+            fn get_num_property(&self, property: &Number) -> Option<MaybeImprecise> {
+                (*self).get_num_property(property)
+            }
+            fn get_text_property(&self, property: &Text) -> Option<String> {
+                (*self).get_text_property(property)
+            }
+            fn get_vec_property(&self, property: &Array) -> Option<&[String]> {
+                (*self).get_vec_property(property)
+            }
+            fn get_keywords(&self) -> Option<&[Keyword]> {
+                (*self).get_keywords()
+            }
+            fn get_name(&self) -> Option<&str> {
+                (*self).get_name()
+            }
+            fn get_description(&self) -> Option<&RichString> {
+                (*self).get_description()
+            }
+            fn get_type(&self) -> Option<&str> {
+                (*self).get_type()
+            }
+            fn get_kins(&self) -> Option<&[String]> {
+                (*self).get_kins()
+            }
+            fn get_flavor_text(&self) -> Option<&str> {
+                (*self).get_flavor_text()
+            }
+        }
+
+        let cards: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "toad", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "devours", "data": {"type": "CardId", "kins": ["insect"]}}]},
+                {"id": "ant", "name": "Ant", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["insect"]},
+                {"id": "moth", "name": "Moth", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["insect"]},
+                {"id": "rat", "name": "Rat", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["rodent"]}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+        let query = query_parser("db:(n:toad)").expect("couldn't parse query");
+
+        let expected: Vec<String> = search(&query, cards.iter())
+            .iter()
+            .map(|card| card.name.clone())
+            .collect();
+        assert_eq!(expected, ["Ant", "Moth"]);
+
+        let unclonable: Vec<Unclonable> = cards.into_iter().map(Unclonable).collect();
+        let result: Vec<String> = search(&query, unclonable.iter())
+            .iter()
+            .map(|card| card.0.name.clone())
+            .collect();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn test_cost_range() {
+        let card = |name: &str, cost: MaybeImprecise| Card {
+            name: name.to_string(),
+            cost,
+            ..Default::default()
+        };
+        let cards = [
+            card("one", MaybeImprecise::Precise(MaybeVar::Const(1))),
+            card("two", MaybeImprecise::Precise(MaybeVar::Const(2))),
+            card("five", MaybeImprecise::Precise(MaybeVar::Const(5))),
+            card("six", MaybeImprecise::Precise(MaybeVar::Const(6))),
+            card(
+                "more than three",
+                MaybeImprecise::Imprecise(Comparison::GreaterThan(3)),
+            ),
+            card(
+                "more than five",
+                MaybeImprecise::Imprecise(Comparison::GreaterThan(5)),
+            ),
+        ];
+
+        for query in ["cost=2..5", "c:2..5"] {
+            let query = query_parser(query).expect("couldn't parse query");
+            assert_eq!(
+                query.restrictions,
+                [QueryRestriction::Comparison(
+                    Number::Cost,
+                    Comparison::Range { low: 2, high: 5 }
+                )]
+            );
+            let result: Vec<&str> = search(&query, cards.iter())
+                .iter()
+                .map(|card| card.name.as_str())
+                .collect();
+            assert_eq!(result, ["five", "more than three", "two"]);
+        }
+        assert!(query_parser("c:5..2").is_err());
+        assert_eq!(Comparison::Range { low: 2, high: 5 }.to_string(), "2..5");
+
+        let ranged = card(
+            "ranged",
+            MaybeImprecise::Imprecise(Comparison::Range { low: 2, high: 4 }),
+        );
+        let cache = Cache::default();
+        let query = query_parser("c>3").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&ranged, &query, &cards.iter(), &cache),
+            Ternary::True
+        );
+        let query = query_parser("c>4").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&ranged, &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+
+        let mut sorted = [
+            MaybeImprecise::Imprecise(Comparison::Range { low: 2, high: 6 }),
+            MaybeImprecise::Precise(MaybeVar::Const(3)),
+            MaybeImprecise::Imprecise(Comparison::Range { low: 2, high: 4 }),
+        ];
+        sorted.sort_by(ImpreciseOrd::imprecise_cmp);
+        assert_eq!(
+            sorted.each_ref().map(ToString::to_string),
+            ["2..4", "2..6", "3"]
+        );
+        let json = serde_json::to_string(&sorted[0]).expect("couldn't serialize range");
+        assert_eq!(json, r#""2..4""#);
+        assert_eq!(
+            serde_json::from_str::<MaybeImprecise>(&json).expect("couldn't read range"),
+            sorted[0]
+        );
+    }
+
+    #[test]
+    fn test_abilities_text_and_count() {
+        let card = |name: &str, abilities: &[&str]| Card {
+            name: name.to_string(),
+            abilities: abilities.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card("Scholar", &["Draw a card.", "Discard a card."]),
+            card("Scribe", &["Draw a card."]),
+            card("Brute", &["Deal 2 damage.", "Gain 1 health."]),
+            card("Ant", &[]),
+        ];
+        let names = |query: &str| -> Vec<String> {
+            search(
+                &query_parser(query).expect("couldn't parse query"),
+                cards.iter(),
+            )
+            .iter()
+            .map(|card| card.name.clone())
+            .collect()
+        };
+
+        assert_eq!(names("ability:draw"), ["Scholar", "Scribe"]);
+        assert_eq!(names("count:abilities>=2"), ["Brute", "Scholar"]);
+        assert_eq!(names("ability:draw count:abilities>=2"), ["Scholar"]);
+        assert_eq!(names("count:abilities=0"), ["Ant"]);
+        assert!(query_parser("count:cost>=2").is_err());
+
+        let cache = Cache::default();
+        let query = query_parser("count:abilities>=1").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_variable_stats() {
+        let card = |name: &str, cost: MaybeVar| Card {
+            name: name.to_string(),
+            cost: MaybeImprecise::Precise(cost),
+            ..Default::default()
+        };
+        let cards = [
+            card("variable", MaybeVar::Var('X')),
+            card("zero", MaybeVar::Const(0)),
+            card("offset", MaybeVar::VarPlus('X', 1)),
+        ];
+        let cache = Cache::default();
+
+        for query in ["cost=X", "cost:var", "c:x"] {
+            let query = query_parser(query).expect("couldn't parse query");
+            let results: Vec<Ternary> = cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect();
+            assert_eq!(results, [Ternary::True, Ternary::False, Ternary::True]);
+        }
+
+        let query = query_parser("cost=0").expect("couldn't parse query");
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::True, Ternary::False]);
+    }
+
+    #[test]
+    fn test_set_and_tag() {
+        let card = |set: &str, other: &[&str]| Card {
+            set: set.to_string(),
+            other: other.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let promo = card("Core", &["promo"]);
+        let plain = card("Core", &[]);
+        let other_set = card("Expansion", &["promo"]);
+        let id = CardId::default();
+        let cards = [promo.clone(), plain.clone(), other_set.clone()];
+        let cache = Cache::default();
+
+        let built = Query::default().in_set("core").with_tag("Promo");
+        let parsed = query_parser("set:core other:promo").expect("couldn't parse query");
+        for query in [&built, &parsed] {
+            let results: Vec<Ternary> = [&promo, &plain, &other_set]
+                .into_iter()
+                .map(|card| matches_query(card, query, &cards.iter(), &cache))
+                .collect();
+            assert_eq!(results, [Ternary::True, Ternary::False, Ternary::False]);
+            assert_eq!(
+                matches_query(&id, query, &cards.iter(), &cache),
+                Ternary::Void
+            );
+        }
+
+        let named = Query::default().in_set("core");
+        let named = Query {
+            restrictions: [
+                named.restrictions,
+                vec![QueryRestriction::Equals(Text::Name, String::new())],
+            ]
+            .concat(),
+            ..named
+        };
+        let named_id = CardId {
+            name: Some("Ant".to_string()),
+            ..Default::default()
+        };
+        // A property the `CardId` doesn't specify outweighs one it fails.
+        assert_eq!(
+            matches_query(&named_id, &named, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_flavor_text_search() {
+        let query = query_parser("ft:moon").expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions,
+            [QueryRestriction::Contains(
+                Text::FlavorText,
+                "moon".to_string()
+            )]
+        );
+
+        let card = |flavor_text: &str| Card {
+            flavor_text: flavor_text.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("Under the Moon."), card("")];
+        let cache = Cache::default();
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::False]);
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_search_scored() {
+        let card = |name: &str| Card {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        let cards = [card("Bee"), card("Giant Ant"), card("Ant"), card("Antlion")];
+        let query = Query {
+            name: "ant".to_string(),
+            restrictions: vec![],
+            sort: Sort::Fuzzy,
+        };
+
+        let scored = search_scored(&query, &cards);
+        let names: Vec<&str> = scored.iter().map(|(card, _)| card.name.as_str()).collect();
+        let plain: Vec<&str> = search(&query, &cards)
+            .iter()
+            .map(|card| card.name.as_str())
+            .collect();
+        assert_eq!(names, plain);
+        assert_eq!(names[0], "Ant");
+        assert!(scored.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        assert!(scored
+            .iter()
+            .all(|(card, score)| (*score - weighted_compare(*card, "ant")).abs() < f32::EPSILON));
+
+        let unsorted = Query {
+            sort: Sort::None,
+            ..query
+        };
+        assert!(search_scored(&unsorted, &cards)
+            .iter()
+            .all(|(_, score)| *score == 0.0));
+    }
+
+    #[test]
+    fn test_status_search() {
+        let card = |legality: &[(&str, &str)]| Card {
+            legality: legality
+                .iter()
+                .map(|(format, status)| ((*format).to_string(), (*status).to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        let cards = [
+            card(&[("standard", "legal"), ("eternal", "Banned")]),
+            card(&[("standard", "legal")]),
+            card(&[]),
+        ];
+        let cache = Cache::default();
+
+        let query = query_parser("status:banned").expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions,
+            [QueryRestriction::HasStatus("banned".to_string())]
+        );
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::False, Ternary::False]);
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_weights() {
+        let card = |name: &str, r#type: &str| Card {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            ..Default::default()
+        };
+        let named = card("Ant", "command");
+        let typed = card("Bee", "ant");
+
+        assert!(weighted_compare(&named, "ant") > weighted_compare(&typed, "ant"));
+        assert!(
+            (weighted_compare(&named, "ant")
+                - weighted_compare_with(&named, "ant", &FuzzyWeights::default()))
+            .abs()
+                < f32::EPSILON
+        );
+
+        let weights = FuzzyWeights {
+            name: 0.,
+            ..Default::default()
+        };
+        assert!(
+            weighted_compare_with(&named, "ant", &weights)
+                < weighted_compare_with(&typed, "ant", &weights)
+        );
+    }
+
+    #[test]
+    fn test_search_sorted_by() {
+        let cards: Vec<Card> = ["Ant", "Bee", "Moth", "Toad"]
+            .into_iter()
+            .map(|name| Card {
+                name: name.to_string(),
+                r#type: "creature".to_string(),
+                ..Default::default()
+            })
+            .collect();
+        let popularity = HashMap::from([("Ant", 2), ("Bee", 9), ("Moth", 5)]);
+        let query = query_parser("t:creature -n:toad sort:name").expect("couldn't parse query");
+
+        let results: Vec<&str> = search_sorted_by(&query, &cards, |a, b| {
+            popularity[b.name.as_str()].cmp(&popularity[a.name.as_str()])
+        })
+        .iter()
+        .map(|card| card.name.as_str())
+        .collect();
+        assert_eq!(results, ["Bee", "Moth", "Ant"]);
+    }
+
+    #[test]
+    fn test_fuzzy_flavor_text_and_functions() {
+        let card = |name: &str, flavor_text: &str, functions: &[&str]| Card {
+            name: name.to_string(),
+            flavor_text: flavor_text.to_string(),
+            functions: functions.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        };
+        let moth = card("Moth", "Drawn to the lamp.", &[]);
+        let owl = card("Owl", "", &["draw cards"]);
+        let bee = card("Bee", "", &[]);
+
+        assert!(fuzzy(&moth, "LAMP"));
+        assert!(fuzzy(&owl, "draw cards"));
+        assert!(!fuzzy(&bee, "lamp"));
+        assert!(!fuzzy(&CardId::default(), "lamp"));
+
+        assert!(
+            weighted_compare(&moth, "drawn to the lamp")
+                > weighted_compare(&bee, "drawn to the lamp")
+        );
+        assert!(weighted_compare(&owl, "draw cards") > weighted_compare(&bee, "draw cards"));
+        assert!(weighted_compare(&CardId::default(), "drawn to the lamp").abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_match_any_identity() {
+        let card = |id: &str, name: &str, cost: usize| Card {
+            id: id.to_string(),
+            name: name.to_string(),
+            cost: MaybeImprecise::Precise(MaybeVar::Const(cost)),
+            ..Default::default()
+        };
+        let pool = [
+            card("ant", "Ant", 1),
+            card("bee", "Bee", 2),
+            card("big-bee", "Big Bee", 4),
+            card("moth", "Moth", 1),
+            card("toad", "Toad", 2),
+        ];
+        let ids = [
+            CardId {
+                name: Some("bee".to_string()),
+                cost: Some(MaybeImprecise::Precise(MaybeVar::Const(2))),
+                ..Default::default()
+            },
+            CardId {
+                name: Some("Toad".to_string()),
+                ..Default::default()
+            },
+            CardId {
+                name: Some("Ant".to_string()),
+                cost: Some(MaybeImprecise::Precise(MaybeVar::Const(3))),
+                ..Default::default()
+            },
+        ];
+
+        let ids: Vec<&str> = match_any_identity(&ids, &pool)
+            .iter()
+            .map(|card| card.id.as_str())
+            .collect();
+        assert_eq!(ids, ["bee", "toad"]);
+        assert!(match_any_identity(&[], &pool).is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_scope() {
+        let card = |name: &str, description: &str| Card {
+            name: name.to_string(),
+            description: RichString {
+                elements: vec![RichElement::String(description.to_string())],
+            },
+            ..Default::default()
+        };
+        let ant = card("Ant", "Digs tunnels.");
+        let mole = card("Mole", "Digs tunnels for ants.");
+        let names = FuzzyScope {
+            name: true,
+            ..FuzzyScope::none()
+        };
+
+        assert!(fuzzy(&mole, "ant"));
+        assert!(fuzzy_scoped(&ant, "ant", &names));
+        assert!(!fuzzy_scoped(&mole, "ant", &names));
+        assert!(!fuzzy_scoped(&ant, "ant", &FuzzyScope::none()));
+
+        let weights = FuzzyWeights::default().scoped(&names);
+        let query = "digs tunnels for ants";
+        let undescribed = card("Mole", "");
+        assert!(
+            (weighted_compare_with(&mole, query, &weights)
+                - weighted_compare_with(&undescribed, query, &weights))
+            .abs()
+                < f32::EPSILON
+        );
+        assert!(weighted_compare(&mole, query) > weighted_compare_with(&mole, query, &weights));
+        assert!(
+            weighted_compare_with(&ant, "ant", &weights)
+                > weighted_compare_with(&mole, "ant", &weights)
+        );
+    }
+
+    #[test]
+    fn test_legal_search() {
+        let cards = [
+            Card::builder()
+                .name("Ant")
+                .legality("Standard", "Legal")
+                .build(),
+            Card::builder()
+                .name("Bee")
+                .legality("standard", "banned")
+                .legality("eternal", "legal")
+                .build(),
+            Card::builder().name("Moth").build(),
+        ];
+        let cache = Cache::default();
+
+        for (search, expected) in [
+            (
+                "legal:standard",
+                [Ternary::True, Ternary::False, Ternary::False],
+            ),
+            ("l:eternal", [Ternary::False, Ternary::True, Ternary::False]),
+            (
+                "legal:draft",
+                [Ternary::False, Ternary::False, Ternary::False],
+            ),
+        ] {
+            let query = query_parser(search).expect("couldn't parse query");
+            let results: Vec<Ternary> = cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect();
+            assert_eq!(results, expected, "{search}");
+            assert_eq!(
+                matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+                Ternary::Void
+            );
+        }
+
+        let displayed = |search: &str| {
+            query_parser(search)
+                .expect("couldn't parse query")
+                .restrictions[0]
+                .to_string()
+        };
+        assert_eq!(
+            displayed("legal:standard"),
+            r#"which are legal in "standard""#
+        );
+        assert_eq!(
+            displayed("status:banned"),
+            r#"which are "banned" in some format"#
+        );
+    }
+
+    #[test]
+    fn test_ability_search() {
+        let cards = [
+            Card::builder()
+                .name("Owl")
+                .ability("When played, draw a card.")
+                .build(),
+            Card::builder().name("Bee").ability("Flying.").build(),
+        ];
+        let cache = Cache::default();
+
+        let query = query_parser(r#"ab:"draw""#).expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions,
+            [QueryRestriction::Has(Array::Abilities, "draw".to_string())]
+        );
+        assert_eq!(
+            query.restrictions[0].to_string(),
+            r#"with an ability that mentions "draw""#
+        );
+        let results: Vec<Ternary> = cards
+            .iter()
+            .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+            .collect();
+        assert_eq!(results, [Ternary::True, Ternary::False]);
+        assert_eq!(
+            matches_query(&CardId::default(), &query, &cards.iter(), &cache),
+            Ternary::Void
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_search_par() {
+        use super::search_par;
+
+        let mut cards: Vec<Card> = serde_json::from_str(
+            &std::fs::read_to_string("tests/search.json").expect("Couldn't load search.json"),
+        )
+        .expect("Couldn't convert search.json to a vec of cards");
+        let mut reprint = cards[0].clone();
+        reprint.id.push_str("-reprint");
+        cards.push(reprint);
+        assert_eq!(
+            search_par(
+                &query_parser("is:reprint").expect("couldn't parse query"),
+                &cards
+            )
+            .len(),
+            2
+        );
+        for search_text in [
+            "t:creature",
+            "c>=2 sort:name",
+            "k:insect or h=1",
+            "-(p<2) sortd:cost",
+            "devours:(n:toad)",
+            "devouredby:(t:creature)",
+            "is:reprint",
+            "-is:reprint c<3",
+            "devours:(is:reprint)",
+        ] {
+            let query = query_parser(search_text).expect("couldn't parse query");
+            let sequential: Vec<&str> = search(&query, &cards)
+                .iter()
+                .map(|card| card.id.as_str())
+                .collect();
+            let parallel: Vec<&str> = search_par(&query, &cards)
+                .iter()
+                .map(|card| card.id.as_str())
+                .collect();
+            assert_eq!(sequential, parallel, "{search_text}");
+        }
+    }
+
+    #[test]
+    fn test_devoured_by_cache_keys() {
+        let devourer = |name: &str, devours: &str| {
+            Card::builder()
+                .name(name)
+                .keyword(Keyword {
+                    name: "devours".to_string(),
+                    data: Some(KeywordData::CardId(CardId::builder().name(devours).build())),
+                })
+                .build()
+        };
+        let cards = [
+            devourer("Ant Bee", "Toad"),
+            devourer("A/, whose Name matches /B", "Frog"),
+            Card::builder().name("Toad").build(),
+            Card::builder().name("Frog").build(),
+        ];
+        let regex = |pattern: &str| {
+            QueryRestriction::Regex(Text::Name, Regex::new(pattern).expect("invalid regex"))
+        };
+        let devoured_by = |restrictions: Vec<QueryRestriction>| Query {
+            name: String::new(),
+            restrictions: vec![QueryRestriction::DevouredBy(Query {
+                name: String::new(),
+                restrictions,
+                sort: Sort::None,
+            })],
+            sort: Sort::None,
+        };
+        let both = devoured_by(vec![regex("(?i)a"), regex("b")]);
+        let weird = devoured_by(vec![regex("(?i)a/, whose Name matches /b")]);
+        // Displaying escapes the slash, so the two don't read the same anymore either.
+        assert_ne!(both.to_string(), weird.to_string());
+
+        // Ids aren't quoted when displayed, so an id with a comma reads the same as two ids.
+        let ids = |ids: &[&str]| Query {
+            name: String::new(),
+            restrictions: vec![QueryRestriction::IdIn(
+                ids.iter().map(ToString::to_string).collect(),
+            )],
+            sort: Sort::None,
+        };
+        let two_ids = ids(&["ant", "bee"]);
+        let one_id = ids(&["ant, bee"]);
+        assert_eq!(two_ids.to_string(), one_id.to_string());
+        assert_ne!(devoured_by_key(&two_ids), devoured_by_key(&one_id));
+
+        let cache = Cache::default();
+        assert_eq!(
+            matches_query(&cards[2], &both, &cards.iter(), &cache),
+            Ternary::True
+        );
+        assert_eq!(
+            matches_query(&cards[2], &weird, &cards.iter(), &cache),
+            Ternary::False
+        );
+        assert_eq!(
+            matches_query(&cards[3], &weird, &cards.iter(), &cache),
+            Ternary::True
+        );
+    }
+
+    #[test]
+    fn test_field_comparison() {
+        let stat = |x: usize| MaybeImprecise::Precise(MaybeVar::Const(x));
+        let card = |name: &str, r#type: &str, power: MaybeImprecise, health: usize| Card {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            power,
+            health: stat(health),
+            ..Default::default()
+        };
+        let cards = [
+            card("Wasp", "creature", stat(4), 2),
+            card("Turtle", "creature", stat(2), 4),
+            card("Ant", "creature", stat(2), 2),
+            card(
+                "Moth",
+                "creature",
+                MaybeImprecise::Imprecise(Comparison::GreaterThan(2)),
+                3,
+            ),
+            card("Shuffle", "command", stat(0), 0),
+        ];
+        let cache = Cache::default();
+        let results = |query: &str| -> Vec<Ternary> {
+            let query = query_parser(query).expect("couldn't parse query");
+            cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect()
+        };
+
+        assert_eq!(
+            results("p>h"),
+            [
+                Ternary::True,
+                Ternary::False,
+                Ternary::False,
+                Ternary::True,
+                Ternary::Void
+            ]
+        );
+        assert_eq!(results("power>h"), results("p>health"));
+        assert_eq!(
+            results("power<=health"),
+            [
+                Ternary::False,
+                Ternary::True,
+                Ternary::True,
+                Ternary::False,
+                Ternary::Void
+            ]
+        );
+        assert_eq!(
+            results("p!=h"),
+            [
+                Ternary::True,
+                Ternary::True,
+                Ternary::False,
+                Ternary::True,
+                Ternary::Void
+            ]
+        );
+
+        let query = query_parser("power>=health").expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions,
+            [QueryRestriction::FieldComparison(
+                Number::Power,
+                ComparisonOp::GreaterThanOrEqual,
+                Number::Health
+            )]
+        );
+        assert_eq!(
+            query.restrictions[0].to_string(),
+            "with Power greater than or equal to their Health"
+        );
+        assert!(query_parser("p>name").is_err());
+        assert!(query_parser("p>abc").is_err());
+
+        // A single letter names a property, not a variable, unless it's `x`.
+        for (query, property, other) in [
+            ("p=h", Number::Power, Number::Health),
+            ("power=h", Number::Power, Number::Health),
+            ("p:h", Number::Power, Number::Health),
+            ("c=p", Number::Cost, Number::Power),
+        ] {
+            let query = query_parser(query).expect("couldn't parse query");
+            assert_eq!(
+                query.restrictions,
+                [QueryRestriction::FieldComparison(
+                    property,
+                    ComparisonOp::Equal,
+                    other
+                )]
+            );
+        }
+        assert_eq!(
+            results("p=h"),
+            [
+                Ternary::False,
+                Ternary::False,
+                Ternary::True,
+                Ternary::False,
+                Ternary::Void
+            ]
+        );
+    }
+
+    #[test]
+    fn test_has_property() {
+        let stat = |x: usize| MaybeImprecise::Precise(MaybeVar::Const(x));
+        let wasp = Card::builder()
+            .name("Wasp")
+            .type_("creature")
+            .health(stat(2))
+            .build();
+        let shuffle = Card::builder().name("Shuffle").type_("command").build();
+        let cards = [wasp, shuffle];
+        let cache = Cache::default();
+        let results = |query: &str| -> Vec<Ternary> {
+            let query = query_parser(query).expect("couldn't parse query");
+            cards
+                .iter()
+                .map(|card| matches_query(card, &query, &cards.iter(), &cache))
+                .collect()
+        };
+
+        assert_eq!(results("has:health"), [Ternary::True, Ternary::False]);
+        assert_eq!(results("-has:health"), [Ternary::False, Ternary::True]);
+        assert_eq!(results("has:cost"), [Ternary::True, Ternary::True]);
+        assert_eq!(results("has:flavortext"), [Ternary::True, Ternary::True]);
+        assert_eq!(results("has:kin"), [Ternary::True, Ternary::True]);
+
+        let with_health = CardId::builder().health(stat(2)).build();
+        let without_health = CardId::builder().name("Wasp").build();
+        let query = query_parser("has:h").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&with_health, &query, &cards.iter(), &cache),
+            Ternary::True
+        );
+        assert_eq!(
+            matches_query(&without_health, &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+        let query = query_parser("has:kin").expect("couldn't parse query");
+        assert_eq!(
+            matches_query(&without_health, &query, &cards.iter(), &cache),
+            Ternary::False
+        );
+
+        assert_eq!(
+            query_parser("has:health")
+                .expect("couldn't parse query")
+                .restrictions,
+            [QueryRestriction::HasNumProperty(Number::Health)]
+        );
+        assert!(matches!(
+            query_parser("has:sort"),
+            Err(Errors::UnknownHasParam(param)) if param == "sort"
+        ));
+        assert!(matches!(
+            query_parser("has:flipcost"),
+            Err(Errors::UnknownHasParam(param)) if param == "flipcost"
+        ));
+    }
+}
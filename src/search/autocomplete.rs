@@ -0,0 +1,96 @@
+use std::collections::HashSet;
+
+use crate::cards::properties::Read;
+
+use super::query_parser::PROPERTY_NAMES;
+
+/// Something a partially typed word could be completed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Suggestion<'a> {
+    Property(&'static str),
+    Kin(&'a str),
+    Keyword(&'a str),
+}
+
+/// Whether `candidate` starts with `partial`, ignoring ASCII casing.
+fn starts_with(candidate: &str, partial: &str) -> bool {
+    candidate
+        .get(..partial.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(partial))
+}
+
+/// Suggests the property names, kins and keyword names that start with `partial`. Kins and keywords are taken from `cards`, and each one is only suggested once.
+///
+/// Properties come first, followed by kins and then keywords, in the order they were found. An empty `partial` suggests nothing.
+pub fn autocomplete<'a, C>(
+    partial: &str,
+    cards: impl IntoIterator<Item = &'a C>,
+) -> Vec<Suggestion<'a>>
+where
+    C: Read + 'a,
+{
+    if partial.is_empty() {
+        return vec![];
+    }
+
+    let mut suggestions: Vec<Suggestion> = PROPERTY_NAMES
+        .iter()
+        .filter(|name| starts_with(name, partial))
+        .map(|name| Suggestion::Property(name))
+        .collect();
+    let mut kins = vec![];
+    let mut keywords = vec![];
+    let mut seen = HashSet::new();
+
+    for card in cards {
+        for kin in card.get_kins().unwrap_or_default() {
+            if starts_with(kin, partial) && seen.insert(Suggestion::Kin(kin)) {
+                kins.push(Suggestion::Kin(kin));
+            }
+        }
+        for keyword in card.get_keywords().unwrap_or_default() {
+            let name = keyword.name.as_str();
+            if starts_with(name, partial) && seen.insert(Suggestion::Keyword(name)) {
+                keywords.push(Suggestion::Keyword(name));
+            }
+        }
+    }
+
+    suggestions.append(&mut kins);
+    suggestions.append(&mut keywords);
+    suggestions
+}
+
+#[cfg(test)]
+mod test {
+    use crate::cards::{Card, Keyword};
+
+    use super::{autocomplete, Suggestion};
+
+    #[test]
+    fn test_autocomplete() {
+        let card = |kins: &[&str], keyword: &str| Card {
+            kins: kins.iter().map(ToString::to_string).collect(),
+            keywords: vec![Keyword {
+                name: keyword.to_string(),
+                data: None,
+            }],
+            ..Default::default()
+        };
+        let cards = [
+            card(&["ant", "insect"], "infest"),
+            card(&["bee", "insect"], "poison"),
+        ];
+
+        assert_eq!(
+            autocomplete("po", &cards),
+            [Suggestion::Property("power"), Suggestion::Keyword("poison")]
+        );
+        assert_eq!(autocomplete("ins", &cards), [Suggestion::Kin("insect")]);
+        assert_eq!(
+            autocomplete("IN", &cards),
+            [Suggestion::Kin("insect"), Suggestion::Keyword("infest")]
+        );
+        assert!(autocomplete("", &cards).is_empty());
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use crate::{cards::properties::Number, numbers::Comparison};
+
+use super::{Query, QueryRestriction, Sort};
+
+impl Query {
+    /// Rewrites the query into a deterministic form, so that two queries that match the same cards compare equal more often.
+    ///
+    /// Nested queries are canonicalized first and lose their name and sort, since only their restrictions are used for matching. Then groups are flattened into the query, double negations are removed, comparisons on the same property that point in the same direction are merged into the tightest one, and restrictions are deduplicated and sorted.
+    #[must_use]
+    pub fn canonicalize(self) -> Self {
+        let mut restrictions = vec![];
+        for restriction in self.restrictions {
+            flatten_into(&mut restrictions, canonicalize_restriction(restriction));
+        }
+
+        let mut restrictions = merge_comparisons(restrictions);
+        restrictions.sort_by_cached_key(|restriction| format!("{restriction:?}"));
+        restrictions.dedup();
+
+        Self {
+            name: self.name,
+            restrictions,
+            sort: self.sort,
+        }
+    }
+}
+
+/// Canonicalizes a query nested inside a restriction.
+fn canonicalize_nested(query: Query) -> Query {
+    Query {
+        name: String::new(),
+        sort: Sort::None,
+        ..query
+    }
+    .canonicalize()
+}
+
+fn canonicalize_restriction(restriction: QueryRestriction) -> QueryRestriction {
+    match restriction {
+        QueryRestriction::Devours(query) => QueryRestriction::Devours(canonicalize_nested(query)),
+        QueryRestriction::DevouredBy(query) => {
+            QueryRestriction::DevouredBy(canonicalize_nested(query))
+        }
+        QueryRestriction::Not(query) => QueryRestriction::Not(canonicalize_nested(query)),
+        QueryRestriction::LenientNot(query) => {
+            QueryRestriction::LenientNot(canonicalize_nested(query))
+        }
+        QueryRestriction::Group(query) => QueryRestriction::Group(canonicalize_nested(query)),
+        QueryRestriction::Or(a, b) => {
+            let (a, b) = sorted_pair(canonicalize_nested(a), canonicalize_nested(b));
+            QueryRestriction::Or(a, b)
+        }
+        QueryRestriction::Xor(a, b) => {
+            let (a, b) = sorted_pair(canonicalize_nested(a), canonicalize_nested(b));
+            QueryRestriction::Xor(a, b)
+        }
+        QueryRestriction::Comparison(property, comparison) => {
+            QueryRestriction::Comparison(property, inclusive(comparison))
+        }
+        restriction => restriction,
+    }
+}
+
+/// Pushes a canonical `restriction` into `restrictions`, splicing in the contents of groups and double negations.
+fn flatten_into(restrictions: &mut Vec<QueryRestriction>, restriction: QueryRestriction) {
+    match restriction {
+        QueryRestriction::Group(query) => restrictions.extend(query.restrictions),
+        QueryRestriction::Not(query) => match <[QueryRestriction; 1]>::try_from(query.restrictions)
+        {
+            Ok([QueryRestriction::Not(inner)]) => restrictions.extend(inner.restrictions),
+            Ok([restriction]) => restrictions.push(QueryRestriction::Not(Query {
+                restrictions: vec![restriction],
+                ..query
+            })),
+            Err(inner) => restrictions.push(QueryRestriction::Not(Query {
+                restrictions: inner,
+                ..query
+            })),
+        },
+        restriction => restrictions.push(restriction),
+    }
+}
+
+/// Orders the operands of commutative restrictions.
+fn sorted_pair(a: Query, b: Query) -> (Query, Query) {
+    if format!("{a:?}") <= format!("{b:?}") {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Turns strict bounds into inclusive ones, so that `>2` and `>=3` are written the same way.
+///
+/// `<0` is left alone, as it admits no numbers.
+const fn inclusive(comparison: Comparison) -> Comparison {
+    match comparison {
+        Comparison::GreaterThan(x) => Comparison::GreaterThanOrEqual(x.saturating_add(1)),
+        Comparison::LowerThan(x) if x > 0 => Comparison::LowerThanOrEqual(x - 1),
+        comparison => comparison,
+    }
+}
+
+/// Keeps only the tightest lower bound and the tightest upper bound of each numeric property.
+///
+/// A card that passes the tightest bound always passes the looser ones, and a card that lacks the property fails all of them the same way.
+fn merge_comparisons(restrictions: Vec<QueryRestriction>) -> Vec<QueryRestriction> {
+    let mut lower: HashMap<Number, usize> = HashMap::new();
+    let mut upper: HashMap<Number, usize> = HashMap::new();
+    let mut merged = vec![];
+
+    for restriction in restrictions {
+        match restriction {
+            QueryRestriction::Comparison(property, Comparison::GreaterThanOrEqual(x)) => {
+                let bound = lower.entry(property).or_insert(x);
+                *bound = (*bound).max(x);
+            }
+            QueryRestriction::Comparison(property, Comparison::LowerThanOrEqual(x)) => {
+                let bound = upper.entry(property).or_insert(x);
+                *bound = (*bound).min(x);
+            }
+            restriction => merged.push(restriction),
+        }
+    }
+
+    merged.extend(lower.into_iter().map(|(property, x)| {
+        QueryRestriction::Comparison(property, Comparison::GreaterThanOrEqual(x))
+    }));
+    merged.extend(upper.into_iter().map(|(property, x)| {
+        QueryRestriction::Comparison(property, Comparison::LowerThanOrEqual(x))
+    }));
+    merged
+}
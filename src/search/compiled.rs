@@ -0,0 +1,146 @@
+use crate::cards::properties::Read;
+
+use super::{
+    devoured_by_key, is_devoured_by, matches_query, Cache, Query, QueryRestriction, Ternary,
+};
+
+/// A `Query` prepared to be matched against many cards.
+///
+/// Groups are flattened into the query, and the cache keys of the `devouredby` restrictions at its top level or in its groups are worked out once instead of once per card. Ones inside `NOT`, `OR` or `XOR` are still keyed per card. It matches the same cards as the query it was compiled from.
+#[derive(Debug, Clone)]
+pub struct CompiledQuery {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    /// Restrictions that are matched as they are.
+    Restrictions(Query),
+    /// A `devouredby` restriction, along with its cache key.
+    DevouredBy { query: Query, key: String },
+}
+
+impl Query {
+    /// Prepares the query to be matched against many cards. Its name and sort are left out, since they don't affect which cards match.
+    #[must_use]
+    pub fn compile(&self) -> CompiledQuery {
+        let mut restrictions = vec![];
+        flatten_groups(&mut restrictions, &self.restrictions);
+
+        let mut steps = vec![];
+        let mut run = vec![];
+        for restriction in restrictions {
+            if let QueryRestriction::DevouredBy(query) = restriction {
+                if !run.is_empty() {
+                    steps.push(Step::Restrictions(Self::from_restrictions(std::mem::take(
+                        &mut run,
+                    ))));
+                }
+                let key = devoured_by_key(&query);
+                steps.push(Step::DevouredBy { query, key });
+            } else {
+                run.push(restriction);
+            }
+        }
+        if !run.is_empty() {
+            steps.push(Step::Restrictions(Self::from_restrictions(run)));
+        }
+
+        CompiledQuery { steps }
+    }
+}
+
+impl CompiledQuery {
+    /// Does the same as `matches_query` with the query this was compiled from.
+    pub fn matches<'a, C, T, I>(&self, card: &C, cards: &I, cache: &Cache<&'a T>) -> Ternary
+    where
+        C: Read,
+        T: Read + 'a,
+        &'a T: Read,
+        I: IntoIterator<Item = &'a T> + Clone,
+    {
+        self.steps
+            .iter()
+            .fold(Ternary::True, |filtered, step| match step {
+                Step::Restrictions(query) => filtered.and(matches_query(card, query, cards, cache)),
+                Step::DevouredBy { query, key } => {
+                    filtered.and(is_devoured_by(card, query, key, cards, cache))
+                }
+            })
+    }
+}
+
+/// Pushes clones of the `restrictions` into `flattened`, splicing in the contents of groups. Other restrictions that hold queries, like `Not` and `Or`, are pushed whole.
+fn flatten_groups(flattened: &mut Vec<QueryRestriction>, restrictions: &[QueryRestriction]) {
+    for restriction in restrictions {
+        match restriction {
+            QueryRestriction::Group(query) => flatten_groups(flattened, &query.restrictions),
+            restriction => flattened.push(restriction.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cards::{Card, CardId, Keyword, KeywordData},
+        numbers::{MaybeImprecise, MaybeVar},
+        search::{matches_query, query_parser::query_parser, Cache, Ternary},
+    };
+
+    #[test]
+    fn test_compiled_query_matches_like_query() {
+        let kins = ["insect", "amphibian", "reptile", "bird"];
+        let names = ["Ant", "Toad", "Snake", "Owl", "Moth", "Frog"];
+        let cards: Vec<Card> = (0..300)
+            .map(|i| {
+                let stat = |x: usize| MaybeImprecise::Precise(MaybeVar::Const(x));
+                let mut card = Card::builder()
+                    .id(format!("card-{i}"))
+                    .name(format!("{} {}", names[i % names.len()], i % 7))
+                    .type_(if i % 5 == 0 { "command" } else { "creature" })
+                    .cost(stat(i % 6))
+                    .health(stat(i % 4))
+                    .power(stat(i % 3))
+                    .kin(kins[i % kins.len()]);
+                if i % 9 == 0 {
+                    card = card.keyword(Keyword {
+                        name: "devours".to_string(),
+                        data: Some(KeywordData::CardId(
+                            CardId::builder().kin(kins[(i / 9) % kins.len()]).build(),
+                        )),
+                    });
+                }
+                card.build()
+            })
+            .collect();
+
+        for search in [
+            "t:creature c>2",
+            "(k:insect (h>=2 p<2)) OR n:toad",
+            "-(c=1) (k:bird XOR p=0)",
+            "devouredby:(k:reptile) c<4",
+            "(devouredby:(n:owl)) (devouredby:(c>3) h>0)",
+            "-devouredby:(k:insect)",
+        ] {
+            let query = query_parser(search).expect("couldn't parse query");
+            let compiled = query.compile();
+            let cache = Cache::default();
+            let compiled_cache = Cache::default();
+            let mut matched = 0;
+            for card in &cards {
+                let result = matches_query(card, &query, &cards.iter(), &cache);
+                assert_eq!(
+                    compiled.matches(card, &cards.iter(), &compiled_cache),
+                    result,
+                    "{search} on {}",
+                    card.id
+                );
+                if result == Ternary::True {
+                    matched += 1;
+                }
+            }
+            assert!(0 < matched && matched < cards.len(), "{search}");
+        }
+    }
+}
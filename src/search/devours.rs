@@ -0,0 +1,294 @@
+use std::{cell::RefCell, collections::HashSet, ptr};
+
+use crate::cards::{properties::Read, Card, Keyword, KeywordData};
+
+use super::{matches_query, Cache, Query, Sort, Ternary};
+
+/// Creates a query for each card a `card`'s `devours` keywords point to.
+fn devour_targets(card: &impl Read) -> Vec<Query> {
+    card.get_keywords()
+        .unwrap_or_default()
+        .iter()
+        .filter(|keyword| keyword.name == "devours")
+        .filter_map(|keyword| match &keyword.data {
+            Some(KeywordData::CardId(card_id)) => Some(Query {
+                name: String::new(),
+                restrictions: card_id.get_as_query(),
+                sort: Sort::None,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the cards in the `pool` that match any of the identities a `card`'s `devours` keywords point to, in the order of the `pool`.
+#[must_use]
+pub fn devourable_by<'a>(card: &Card, pool: &'a [Card]) -> Vec<&'a Card> {
+    let targets = devour_targets(card);
+    let cache = Cache::default();
+    pool.iter()
+        .filter(|candidate| {
+            targets.iter().any(|target| {
+                matches_query(*candidate, target, &pool.iter(), &cache) == Ternary::True
+            })
+        })
+        .collect()
+}
+
+/// Returns the cards in the `pool` that a `card` devours, and the cards that those devour, and so on.
+///
+/// Every card in the `pool` is only visited once, so devour cycles end.
+pub fn devour_chain<'a, T, I>(card: &impl Read, pool: I) -> Vec<&'a T>
+where
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T>,
+{
+    let pool: Vec<&T> = pool.into_iter().collect();
+    let cache = Cache::default();
+    let mut chain: Vec<&T> = vec![];
+    let mut targets = devour_targets(card);
+
+    while let Some(target) = targets.pop() {
+        for candidate in &pool {
+            if chain.iter().any(|visited| ptr::eq(*visited, *candidate)) {
+                continue;
+            }
+            if matches_query(*candidate, &target, &pool.iter().copied(), &cache) == Ternary::True {
+                chain.push(candidate);
+                targets.extend(devour_targets(*candidate));
+            }
+        }
+    }
+
+    chain
+}
+
+/// Does the same as `devour_chain`, but keeps the cards each card in the `pool` devours in the `cache`, so working out the chains of every card in a search only looks through the pool once per card.
+fn devour_chain_cached<'a, T, I>(card: &impl Read, pool: &I, cache: &Cache<&'a T>) -> Vec<&'a T>
+where
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T> + Clone,
+{
+    let mut chain: Vec<&T> = vec![];
+    let mut pending = devoured_in_pool(card, pool, cache);
+
+    while let Some(candidate) = pending.pop() {
+        if chain.iter().any(|visited| ptr::eq(*visited, candidate)) {
+            continue;
+        }
+        chain.push(candidate);
+        // The cards in the pool outlive the cache, so their addresses can't be reused while it holds them.
+        let key = format!("devourchain:{candidate:p}");
+        if !RefCell::borrow(cache).contains_key(&key) {
+            let devoured = devoured_in_pool(candidate, pool, cache);
+            cache.borrow_mut().insert(key.clone(), devoured);
+        }
+        if let Some(devoured) = RefCell::borrow(cache).get(&key) {
+            pending.extend(devoured.iter().copied());
+        }
+    }
+
+    chain
+}
+
+/// Returns the cards in the `pool` that a `card`'s `devours` keywords point to directly.
+fn devoured_in_pool<'a, T, I>(card: &impl Read, pool: &I, cache: &Cache<&'a T>) -> Vec<&'a T>
+where
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T> + Clone,
+{
+    let targets = devour_targets(card);
+    if targets.is_empty() {
+        return vec![];
+    }
+    pool.clone()
+        .into_iter()
+        .filter(|candidate| {
+            targets
+                .iter()
+                .any(|target| matches_query(*candidate, target, pool, cache) == Ternary::True)
+        })
+        .collect()
+}
+
+/// Does the same as `devour_chain_kins`, sharing the work of finding what each card devours through the `cache`.
+pub(super) fn devour_chain_kins_cached<'a, T, I>(
+    card: &impl Read,
+    pool: &I,
+    cache: &Cache<&'a T>,
+) -> HashSet<String>
+where
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T> + Clone,
+{
+    devour_chain_cached(card, pool, cache)
+        .into_iter()
+        .filter_map(Read::get_kins)
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Returns every distinct kin among the cards in a `card`'s devour chain.
+pub fn devour_chain_kins<'a, T, I>(card: &impl Read, pool: I) -> HashSet<String>
+where
+    T: Read + 'a,
+    &'a T: Read,
+    I: IntoIterator<Item = &'a T>,
+{
+    devour_chain(card, pool)
+        .into_iter()
+        .filter_map(Read::get_kins)
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Returns a `card`'s keywords along with the keywords of every card in its devour chain, without repeats.
+#[must_use]
+pub fn effective_keywords(card: &Card, pool: &[Card]) -> Vec<Keyword> {
+    let mut keywords = card.keywords.clone();
+    for devoured in devour_chain(card, pool) {
+        for keyword in &devoured.keywords {
+            if !keywords.contains(keyword) {
+                keywords.push(keyword.clone());
+            }
+        }
+    }
+    keywords
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use crate::{
+        cards::Card,
+        numbers::Comparison,
+        search::{matches_query, Cache, Query, QueryRestriction, Sort, Ternary},
+    };
+
+    use super::{devour_chain_kins, devour_chain_kins_cached, devourable_by, effective_keywords};
+
+    #[test]
+    fn test_effective_keywords() {
+        let pool: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "toad", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Fly"}}]},
+                {"id": "fly", "name": "Fly", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "flying"}, {"name": "devours", "data": {"type": "CardId", "name": "Toad"}}]},
+                {"id": "moth", "name": "Moth", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "keywords": [{"name": "camouflage"}]}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+
+        let keywords = effective_keywords(&pool[0], &pool);
+        let names: Vec<&str> = keywords
+            .iter()
+            .map(|keyword| keyword.name.as_str())
+            .collect();
+        assert_eq!(names, ["devours", "flying", "devours"]);
+    }
+
+    #[test]
+    fn test_devour_chain_kins() {
+        let pool: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "snake", "name": "Snake", "description": "", "cost": 3, "type": "creature", "health": 3, "defense": 0, "power": 2, "legality": {},
+                 "kins": ["reptile"], "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Toad"}}]},
+                {"id": "toad", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["amphibian"], "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Fly"}}]},
+                {"id": "fly", "name": "Fly", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["fly", "insect"]},
+                {"id": "moth", "name": "Moth", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["moth", "insect"]}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+
+        let kins = devour_chain_kins(&pool[0], &pool);
+        let expected: HashSet<String> = ["amphibian", "fly", "insect"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert_eq!(kins, expected);
+
+        let query = Query {
+            name: String::new(),
+            restrictions: vec![QueryRestriction::DevourChainKins(
+                Comparison::GreaterThanOrEqual(2),
+            )],
+            sort: Sort::None,
+        };
+        let cache = Cache::default();
+        let results: Vec<Ternary> = pool
+            .iter()
+            .map(|card| matches_query(card, &query, &pool.iter(), &cache))
+            .collect();
+        assert_eq!(
+            results,
+            [Ternary::True, Ternary::True, Ternary::False, Ternary::False]
+        );
+    }
+
+    #[test]
+    fn test_devourable_by() {
+        let pool: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "snake", "name": "Snake", "description": "", "cost": 3, "type": "creature", "health": 3, "defense": 0, "power": 2, "legality": {},
+                 "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Toad"}}]},
+                {"id": "toad", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {}},
+                {"id": "toad-reprint", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {}},
+                {"id": "frog", "name": "Frog", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {}}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+
+        let ids: Vec<&str> = devourable_by(&pool[0], &pool)
+            .iter()
+            .map(|card| card.id.as_str())
+            .collect();
+        assert_eq!(ids, ["toad", "toad-reprint"]);
+        assert!(devourable_by(&pool[1], &pool).is_empty());
+    }
+
+    #[test]
+    fn test_devour_chain_kins_cached() {
+        let pool: Vec<Card> = serde_json::from_str(
+            r#"[
+                {"id": "snake", "name": "Snake", "description": "", "cost": 3, "type": "creature", "health": 3, "defense": 0, "power": 2, "legality": {},
+                 "kins": ["reptile"], "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Toad"}}]},
+                {"id": "toad", "name": "Toad", "description": "", "cost": 2, "type": "creature", "health": 2, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["amphibian"], "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Fly"}}]},
+                {"id": "fly", "name": "Fly", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["fly", "insect"], "keywords": [{"name": "devours", "data": {"type": "CardId", "name": "Snake"}}]},
+                {"id": "moth", "name": "Moth", "description": "", "cost": 1, "type": "creature", "health": 1, "defense": 0, "power": 1, "legality": {},
+                 "kins": ["moth", "insect"]}
+            ]"#,
+        )
+        .expect("couldn't read cards");
+
+        let cache = Cache::default();
+        for card in &pool {
+            assert_eq!(
+                devour_chain_kins_cached(card, &pool.iter(), &cache),
+                devour_chain_kins(card, &pool),
+                "{}",
+                card.name
+            );
+        }
+        // What each card in the cycle devours is only worked out once, and the moth isn't devoured by anything.
+        let cached = cache
+            .borrow()
+            .keys()
+            .filter(|key| key.starts_with("devourchain:"))
+            .count();
+        assert_eq!(cached, 3);
+    }
+}
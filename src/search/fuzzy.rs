@@ -1,13 +1,153 @@
-use std::cmp::{max, max_by, Ordering};
+use std::{
+    cmp::{max_by, Ordering},
+    collections::HashMap,
+};
 
 use rust_fuzzy_search::fuzzy_compare;
 
-use crate::{cards::properties::Read, clean_ascii};
+use crate::{
+    cards::properties::{Array, Read},
+    clean_ascii,
+};
 
-/// Compares a card's text with a given string and outputs a value for how much it matched the text, prioritizing in this order: Names, types, descriptions, kins, keywords.
+/// Words that a fuzzy search should also try when the query contains a given word. An empty table doesn't change the results of a search.
+#[derive(Debug, Clone, Default)]
+pub struct SynonymTable {
+    synonyms: HashMap<String, Vec<String>>,
+}
+
+impl SynonymTable {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Makes queries containing `word` also try `synonym` in its place. This only works in one direction.
+    pub fn insert(&mut self, word: &str, synonym: &str) {
+        self.synonyms
+            .entry(clean_ascii(word))
+            .or_default()
+            .push(synonym.to_string());
+    }
+
+    /// Returns `query` followed by every version of it that has one of its words replaced by one of that word's synonyms.
+    #[must_use]
+    pub fn expand(&self, query: &str) -> Vec<String> {
+        let mut expanded = vec![query.to_string()];
+        let words: Vec<&str> = query.split_whitespace().collect();
+        for (index, word) in words.iter().enumerate() {
+            for synonym in self.synonyms.get(&clean_ascii(word)).into_iter().flatten() {
+                let mut replaced = words.clone();
+                replaced[index] = synonym;
+                expanded.push(replaced.join(" "));
+            }
+        }
+        expanded
+    }
+}
+
+/// Which of a card's texts a fuzzy search looks at. By default, it looks at all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct FuzzyScope {
+    pub name: bool,
+    pub r#type: bool,
+    pub description: bool,
+    pub kins: bool,
+    pub keywords: bool,
+    pub flavor_text: bool,
+    pub functions: bool,
+}
+
+impl Default for FuzzyScope {
+    fn default() -> Self {
+        Self {
+            name: true,
+            r#type: true,
+            description: true,
+            kins: true,
+            keywords: true,
+            flavor_text: true,
+            functions: true,
+        }
+    }
+}
+
+impl FuzzyScope {
+    /// A scope that looks at none of the card's texts, to enable them one by one.
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            name: false,
+            r#type: false,
+            description: false,
+            kins: false,
+            keywords: false,
+            flavor_text: false,
+            functions: false,
+        }
+    }
+}
+
+/// How much each of a card's texts counts towards its `weighted_compare_with` score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FuzzyWeights {
+    pub name: f32,
+    pub r#type: f32,
+    pub description: f32,
+    /// Applied to the kin that matches best.
+    pub kins: f32,
+    /// Applied to the keyword that matches best.
+    pub keywords: f32,
+    /// Defaults to 0.8, the lowest of the weights, since flavor text says little about what the card does.
+    pub flavor_text: f32,
+    /// Applied to the function that matches best.
+    pub functions: f32,
+}
+
+/// The weights `weighted_compare` uses.
+impl Default for FuzzyWeights {
+    fn default() -> Self {
+        Self {
+            name: 3.,
+            r#type: 1.8,
+            description: 1.6,
+            kins: 1.5,
+            keywords: 1.,
+            flavor_text: 0.8,
+            functions: 1.,
+        }
+    }
+}
+
+impl FuzzyWeights {
+    /// Zeroes the weights of the texts the `scope` doesn't include, so `weighted_compare_with` ignores them.
+    #[must_use]
+    pub fn scoped(self, scope: &FuzzyScope) -> Self {
+        let keep = |weight: f32, included: bool| if included { weight } else { 0. };
+        Self {
+            name: keep(self.name, scope.name),
+            r#type: keep(self.r#type, scope.r#type),
+            description: keep(self.description, scope.description),
+            kins: keep(self.kins, scope.kins),
+            keywords: keep(self.keywords, scope.keywords),
+            flavor_text: keep(self.flavor_text, scope.flavor_text),
+            functions: keep(self.functions, scope.functions),
+        }
+    }
+}
+
+/// Compares a card's text with a given string and outputs a value for how much it matched the text, prioritizing in this order: Names, types, descriptions, kins, keywords and functions, and flavor text.
+///
 /// Notably, since a card's keywords are also in its description, keywords are ranked slightly higher than they are supposed to. This is not a huge deal, but it is a thing that might be good to be aware of.
 #[must_use]
 pub fn weighted_compare(a: &impl Read, b: &str) -> f32 {
+    weighted_compare_with(a, b, &FuzzyWeights::default())
+}
+
+/// Does the same as `weighted_compare`, but with the given `weights` instead of the default ones.
+#[must_use]
+pub fn weighted_compare_with(a: &impl Read, b: &str, weights: &FuzzyWeights) -> f32 {
     let mut result = 0.0;
 
     if let Some(name) = a.get_name() {
@@ -15,15 +155,16 @@ pub fn weighted_compare(a: &impl Read, b: &str) -> f32 {
             fuzzy_compare(name, b),
             fuzzy_compare(&name.to_lowercase(), &b.to_lowercase()),
             f32::total_cmp,
-        ) * 3.;
+        ) * weights.name;
     }
 
     if let Some(r#type) = a.get_type() {
-        result += fuzzy_compare(&r#type.to_lowercase(), &b.to_lowercase()) * 1.8;
+        result += fuzzy_compare(&r#type.to_lowercase(), &b.to_lowercase()) * weights.r#type;
     }
 
     if let Some(description) = a.get_description() {
-        result += fuzzy_compare(&description.to_string().to_lowercase(), &b.to_lowercase()) * 1.6;
+        result += fuzzy_compare(&description.to_string().to_lowercase(), &b.to_lowercase())
+            * weights.description;
     }
 
     if let Some(kins) = a.get_kins() {
@@ -32,7 +173,7 @@ pub fn weighted_compare(a: &impl Read, b: &str) -> f32 {
             .map(|x| fuzzy_compare(&x.to_lowercase(), &b.to_lowercase()))
             .max_by(|a, b| PartialOrd::partial_cmp(a, b).unwrap_or(Ordering::Less))
             .unwrap_or(0.0)
-            * 1.5;
+            * weights.kins;
     }
 
     if let Some(keywords) = a.get_keywords() {
@@ -40,8 +181,33 @@ pub fn weighted_compare(a: &impl Read, b: &str) -> f32 {
             .iter()
             .map(|x| fuzzy_compare(&x.name.to_lowercase(), &b.to_lowercase()))
             .max_by(|a, b| PartialOrd::partial_cmp(a, b).unwrap_or(Ordering::Less))
-            .unwrap_or(0.0);
+            .unwrap_or(0.0)
+            * weights.keywords;
+    }
+
+    if let Some(flavor_text) = a.get_flavor_text() {
+        result +=
+            fuzzy_compare(&flavor_text.to_lowercase(), &b.to_lowercase()) * weights.flavor_text;
+    }
+
+    if let Some(functions) = a.get_vec_property(&Array::Functions) {
+        result += functions
+            .iter()
+            .map(|x| fuzzy_compare(&x.to_lowercase(), &b.to_lowercase()))
+            .max_by(|a, b| PartialOrd::partial_cmp(a, b).unwrap_or(Ordering::Less))
+            .unwrap_or(0.0)
+            * weights.functions;
     }
 
     result
 }
+
+/// Does the same as `weighted_compare`, but also tries every synonym expansion of `b` and keeps the best result.
+#[must_use]
+pub fn weighted_compare_with_synonyms(a: &impl Read, b: &str, synonyms: &SynonymTable) -> f32 {
+    synonyms
+        .expand(b)
+        .iter()
+        .map(|b| weighted_compare(a, b))
+        .fold(0.0, f32::max)
+}
@@ -1,23 +1,28 @@
-use regex::Regex;
+use std::collections::HashSet;
+
+use regex::{Regex, RegexBuilder};
 
 use crate::{
     cards::properties::{Array, Number, Text},
     numbers::Comparison,
 };
 
-use super::{Errors, Ordering, Query, QueryRestriction, Sort, Ternary};
+use super::{
+    fuzzy::SynonymTable, ComparisonOp, Errors, Ordering, Query, QueryRestriction, Sort, Ternary,
+};
 
 #[derive(Debug)]
 enum Token {
     Word(String),
     Param(String, String),
     RegexParam(String, Regex),
-    SuperParam(String, Vec<Token>),
-    Not(Vec<Token>),
-    LenientNot(Vec<Token>),
-    Group(Vec<Token>),
-    Or(Vec<Token>, Option<Vec<Token>>),
-    Xor(Vec<Token>, Option<Vec<Token>>),
+    SuperParam(String, Vec<Self>),
+    Not(Vec<Self>),
+    LenientNot(Vec<Self>),
+    Group(Vec<Self>),
+    Or(Vec<Self>, Option<Vec<Self>>),
+    Xor(Vec<Self>, Option<Vec<Self>>),
+    And(Vec<Self>, Option<Vec<Self>>),
 }
 
 impl Token {
@@ -54,6 +59,9 @@ impl TokenStack {
             (a, Some(Token::Xor(b, None))) => {
                 self.tokens.push(Token::Xor(b, Some(vec![a])));
             }
+            (a, Some(Token::And(b, None))) => {
+                self.tokens.push(Token::And(b, Some(vec![a])));
+            }
             (a, Some(b)) => {
                 self.tokens.push(b);
                 self.tokens.push(a);
@@ -66,18 +74,44 @@ impl TokenStack {
     }
 }
 
+/// Settings that change how queries are parsed.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions<'a> {
+    /// How many bytes a regex in the query may take up once compiled. Bigger regexes are rejected, so that a query can't make searches arbitrarily expensive.
+    pub regex_size_limit: usize,
+    /// Whether the words outside of parameters are matched one by one, so that cards match if they contain all of them in any order, instead of the whole text at once.
+    pub fuzzy_all_words: bool,
+    /// Whether a lone `|` is read as `OR` and a lone `&` as `AND`, like in `cost:2 | cost:3`. Off by default, so that fuzzy searches for those symbols keep working.
+    pub operator_symbols: bool,
+    /// Words that fuzzy searches also try in place of the ones in the query, so that `dmg` can find cards that say `damage`.
+    pub synonyms: Option<&'a SynonymTable>,
+}
+
+impl Default for ParseOptions<'_> {
+    fn default() -> Self {
+        Self {
+            regex_size_limit: 1 << 20,
+            fuzzy_all_words: false,
+            operator_symbols: false,
+            synonyms: None,
+        }
+    }
+}
+
 enum CharOrEnd {
     Char(char),
     End,
 }
 
 #[allow(clippy::too_many_lines)]
-fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
+fn tokenize_query(q: &str, options: ParseOptions<'_>) -> Result<Vec<Token>, Errors> {
     let mut tokens = TokenStack::default();
     let mut word = String::new();
     let mut mode = TokenMode::Word;
     let mut paren_count = 0;
     let mut polarity = Ternary::True;
+    // Whether the last character was a backslash inside quoted text or a regex. In quoted text it makes the next character literal, and in a regex it lets `\/` stand for a `/`.
+    let mut escaped = false;
     for ch in q.chars().map(CharOrEnd::Char).chain(vec![CharOrEnd::End]) {
         match mode {
             TokenMode::Word => match ch {
@@ -92,6 +126,14 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
                 CharOrEnd::Char(' ') | CharOrEnd::End => {
                     match word.as_str() {
                         "" => (),
+                        "&" if options.operator_symbols => {
+                            let top = tokens.pop().ok_or(Errors::InvalidOr)?;
+                            tokens.push(Token::And(vec![top], None));
+                        }
+                        "|" if options.operator_symbols => {
+                            let top = tokens.pop().ok_or(Errors::InvalidOr)?;
+                            tokens.push(Token::Or(vec![top], None));
+                        }
                         "OR" => {
                             let top = tokens.pop().ok_or(Errors::InvalidOr)?;
                             tokens.push(Token::Or(vec![top], None));
@@ -100,6 +142,10 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
                             let top = tokens.pop().ok_or(Errors::InvalidOr)?;
                             tokens.push(Token::Xor(vec![top], None));
                         }
+                        "AND" => {
+                            let top = tokens.pop().ok_or(Errors::InvalidOr)?;
+                            tokens.push(Token::And(vec![top], None));
+                        }
                         _ => {
                             tokens.push(Token::Word(word).polar_wrap(polarity));
                         }
@@ -114,6 +160,16 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
                     mode = TokenMode::Param(word);
                     word = String::new();
                 }
+                // `~` is only the within operator after a numeric property, like in `power~cost`. Anywhere else it's part of the word, so fuzzy searches like `a~b` keep working.
+                CharOrEnd::Char('~')
+                    if matches!(
+                        get_property_from_name(&word),
+                        Ok(Properties::NumProperty(_))
+                    ) =>
+                {
+                    mode = TokenMode::Param(word);
+                    word = String::from('~');
+                }
                 CharOrEnd::Char(ch @ ('<' | '!' | '>' | '=')) => {
                     if word.is_empty() {
                         return Err(Errors::AttemptedEmptyParamName);
@@ -125,28 +181,46 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
             },
             TokenMode::Param(ref param) => match ch {
                 CharOrEnd::Char(' ') | CharOrEnd::End => {
-                    let tok = Token::Param(param.to_string(), word);
+                    let tok = Token::Param(param.clone(), word);
                     tokens.push(tok.polar_wrap(polarity));
                     polarity = Ternary::True;
                     word = String::new();
                     mode = TokenMode::Word;
                 }
-                CharOrEnd::Char('"') if word.is_empty() => {
-                    mode = TokenMode::QParam(param.to_string());
+                // `name="Green Queen"` keeps the `=` so that the quoted text is matched exactly.
+                CharOrEnd::Char('"') if word.is_empty() || word == "=" => {
+                    mode = TokenMode::QParam(param.clone());
                 }
                 CharOrEnd::Char('/') if word.is_empty() => {
-                    mode = TokenMode::RegexParam(param.to_string());
+                    mode = TokenMode::RegexParam(param.clone());
                 }
                 CharOrEnd::Char('(') if word.is_empty() => {
-                    mode = TokenMode::SParam(param.to_string());
+                    mode = TokenMode::SParam(param.clone());
                 }
                 CharOrEnd::Char(ch) => word.push(ch),
             },
+            TokenMode::RegexParam(_) if escaped => {
+                match ch {
+                    CharOrEnd::Char('/') => word.push('/'),
+                    // Other escapes, like `\w`, mean something to the regex itself.
+                    CharOrEnd::Char(ch) => {
+                        word.push('\\');
+                        word.push(ch);
+                    }
+                    CharOrEnd::End => return Err(Errors::UnclosedRegex),
+                }
+                escaped = false;
+            }
             TokenMode::RegexParam(ref param) => match ch {
+                CharOrEnd::Char('\\') => escaped = true,
                 CharOrEnd::End | CharOrEnd::Char('/') => {
                     let tok = Token::RegexParam(
-                        param.to_string(),
-                        Regex::new(&word.to_lowercase()).map_err(Errors::RegexErr)?,
+                        param.clone(),
+                        RegexBuilder::new(&word.to_lowercase())
+                            .size_limit(options.regex_size_limit)
+                            .dfa_size_limit(options.regex_size_limit)
+                            .build()
+                            .map_err(Errors::RegexErr)?,
                     );
                     tokens.push(tok.polar_wrap(polarity));
                     polarity = Ternary::True;
@@ -155,9 +229,17 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
                 }
                 CharOrEnd::Char(ch) => word.push(ch),
             },
+            TokenMode::QParam(_) if escaped => match ch {
+                CharOrEnd::Char(ch) => {
+                    word.push(ch);
+                    escaped = false;
+                }
+                CharOrEnd::End => return Err(Errors::UnclosedString),
+            },
             TokenMode::QParam(ref param) => match ch {
+                CharOrEnd::Char('\\') => escaped = true,
                 CharOrEnd::Char('"') => {
-                    let tok = Token::Param(param.to_string(), word);
+                    let tok = Token::Param(param.clone(), word);
                     tokens.push(tok.polar_wrap(polarity));
                     polarity = Ternary::True;
                     word = String::new();
@@ -168,7 +250,7 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
             },
             TokenMode::SParam(ref param) => match ch {
                 CharOrEnd::Char(')') if paren_count == 0 => {
-                    let tok = Token::SuperParam(param.to_string(), tokenize_query(&word)?);
+                    let tok = Token::SuperParam(param.clone(), tokenize_query(&word, options)?);
                     tokens.push(tok.polar_wrap(polarity));
                     polarity = Ternary::True;
                     word = String::new();
@@ -187,7 +269,7 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
             },
             TokenMode::Group => match ch {
                 CharOrEnd::Char(')') if paren_count == 0 => {
-                    let tok = Token::Group(tokenize_query(&word)?);
+                    let tok = Token::Group(tokenize_query(&word, options)?);
                     tokens.push(tok.polar_wrap(polarity));
                     polarity = Ternary::True;
                     word = String::new();
@@ -209,7 +291,8 @@ fn tokenize_query(q: &str) -> Result<Vec<Token>, Errors> {
     Ok(tokens.tokens)
 }
 
-fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
+#[allow(clippy::too_many_lines)]
+fn parse_tokens(q: &[Token], options: ParseOptions<'_>) -> Result<Query, Errors> {
     let mut restrictions = vec![];
     let mut name = String::new();
     let mut sort = Sort::Fuzzy;
@@ -219,14 +302,20 @@ fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
                 Properties::StringProperty(property) => {
                     restrictions.push(QueryRestriction::Regex(property, regex.clone()));
                 }
+                Properties::KeywordData => {
+                    restrictions.push(QueryRestriction::HasKwDataRegex(regex.clone()));
+                }
+                Properties::ArrayProperty(property) => {
+                    restrictions.push(QueryRestriction::HasRegex(property, regex.clone()));
+                }
                 _ => return Err(Errors::NonRegexable(field.clone())),
             },
             Token::Or(group1, group2) => match group2 {
                 None => return Err(Errors::InvalidOr),
                 Some(group2) => {
-                    let mut group1 = parse_tokens(group1)?;
+                    let mut group1 = parse_tokens(group1, options)?;
                     group1.sort = Sort::None;
-                    let mut group2 = parse_tokens(group2)?;
+                    let mut group2 = parse_tokens(group2, options)?;
                     group2.sort = Sort::None;
                     restrictions.push(QueryRestriction::Or(group1, group2));
                 }
@@ -234,15 +323,30 @@ fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
             Token::Xor(group1, group2) => match group2 {
                 None => return Err(Errors::InvalidOr),
                 Some(group2) => {
-                    let mut group1 = parse_tokens(group1)?;
+                    let mut group1 = parse_tokens(group1, options)?;
                     group1.sort = Sort::None;
-                    let mut group2 = parse_tokens(group2)?;
+                    let mut group2 = parse_tokens(group2, options)?;
                     group2.sort = Sort::None;
                     restrictions.push(QueryRestriction::Xor(group1, group2));
                 }
             },
+            // An explicit `AND` binds its two sides together, so that it groups like `OR` and `XOR` do.
+            Token::And(group1, group2) => match group2 {
+                None => return Err(Errors::InvalidOr),
+                Some(group2) => {
+                    let mut group = parse_tokens(group1, options)?;
+                    group
+                        .restrictions
+                        .extend(parse_tokens(group2, options)?.restrictions);
+                    restrictions.push(QueryRestriction::Group(Query {
+                        name: String::new(),
+                        restrictions: group.restrictions,
+                        sort: Sort::None,
+                    }));
+                }
+            },
             Token::Group(group) => {
-                let mut group = parse_tokens(group)?;
+                let mut group = parse_tokens(group, options)?;
                 group.sort = Sort::None;
                 restrictions.push(QueryRestriction::Group(group));
             }
@@ -259,26 +363,97 @@ fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
 
                     _ => return Err(Errors::NotSortable),
                 },
+                Properties::MidpointSort(order) => match get_property_from_name(value)? {
+                    Properties::NumProperty(property) => {
+                        sort = Sort::NumericMidpoint(property, order);
+                    }
+                    _ => return Err(Errors::NotSortable),
+                },
                 Properties::NumProperty(property) => {
-                    let cmp = text_comparison_parser(value)?;
-                    restrictions.push(QueryRestriction::Comparison(property, cmp));
+                    if let Some(within) = value.strip_prefix('~') {
+                        let (other, tolerance) = within_parser(within)?;
+                        restrictions.push(QueryRestriction::Within(property, other, tolerance));
+                    } else if let Ok(cmp) = text_comparison_parser(value) {
+                        restrictions.push(QueryRestriction::Comparison(property, cmp));
+                    } else if let Ok((op, other)) = field_comparison_parser(value) {
+                        restrictions.push(QueryRestriction::FieldComparison(property, op, other));
+                    } else if is_variable_value(value) {
+                        restrictions.push(QueryRestriction::IsVariable(property));
+                    } else {
+                        return Err(Errors::InvalidComparisonString);
+                    }
                 }
-                Properties::StringProperty(property) => {
-                    restrictions.push(QueryRestriction::Contains(property, value.clone()));
+                Properties::StringProperty(property) => match value.strip_prefix('=') {
+                    Some(value) => {
+                        restrictions.push(QueryRestriction::Equals(property, value.to_owned()));
+                    }
+                    None => {
+                        restrictions.push(QueryRestriction::Contains(property, value.clone()));
+                    }
+                },
+                Properties::ArrayProperty(property) => match value.strip_prefix('=') {
+                    Some(value) => {
+                        restrictions.push(QueryRestriction::HasExact(property, value.to_owned()));
+                    }
+                    None => restrictions.push(QueryRestriction::Has(property, value.clone())),
+                },
+                Properties::Keywords if value.starts_with("==") => {
+                    restrictions.push(QueryRestriction::KwSet(id_list_parser(&value[2..])));
                 }
-                Properties::ArrayProperty(property) => {
-                    restrictions.push(QueryRestriction::Has(property, value.clone()));
+                Properties::Keywords => {
+                    // `kw=flying` asks for a keyword named exactly "flying", like `n=` and `k=` do for their properties.
+                    let (exact, value) = value
+                        .strip_prefix('=')
+                        .map_or((false, value.as_str()), |value| (true, value));
+                    match value.split_once('=') {
+                        Some((keyword, data)) => restrictions.push(
+                            QueryRestriction::HasKwWithData(keyword.to_owned(), data.to_owned()),
+                        ),
+                        None if exact && value.is_empty() => return Err(Errors::EmptyKeywordName),
+                        None if exact => {
+                            restrictions.push(QueryRestriction::HasKwExact(value.to_owned()));
+                        }
+                        None => restrictions.push(QueryRestriction::HasKw(value.to_owned())),
+                    }
                 }
-                Properties::Keywords => restrictions.push(QueryRestriction::HasKw(value.clone())),
+                Properties::KeywordData => {
+                    restrictions.push(QueryRestriction::HasKwData(value.clone()));
+                }
+                Properties::KeywordNumber => {
+                    let (keyword, comparison) = named_comparison_parser(value)?;
+                    if keyword.is_empty() {
+                        return Err(Errors::EmptyKeywordName);
+                    }
+                    restrictions.push(QueryRestriction::KwNumber(keyword, comparison));
+                }
+                Properties::Count => {
+                    let (property, comparison) = named_comparison_parser(value)?;
+                    match get_property_from_name(&property)? {
+                        Properties::ArrayProperty(property) => {
+                            restrictions.push(QueryRestriction::Count(property, comparison));
+                        }
+                        _ => return Err(Errors::InvalidComparisonString),
+                    }
+                }
+                Properties::Is => restrictions.push(get_is_restriction(value)?),
+                Properties::Missing => restrictions.push(get_missing_restriction(value)?),
+                Properties::Has => restrictions.push(get_has_restriction(value)?),
+                Properties::Links => restrictions.push(QueryRestriction::LinksTo(value.clone())),
+                Properties::Status => restrictions.push(QueryRestriction::HasStatus(value.clone())),
+                Properties::Legal => restrictions.push(QueryRestriction::LegalIn(value.clone())),
+                Properties::DescriptionWords => restrictions.push(
+                    QueryRestriction::DescriptionWords(text_comparison_parser(value)?),
+                ),
+                Properties::Ids => restrictions.push(QueryRestriction::IdIn(id_list_parser(value))),
             },
             Token::SuperParam(param, value) => match param.as_str() {
                 "devours" | "dev" | "de" | "devs" => {
-                    let mut parsed_subquery = parse_tokens(value)?;
+                    let mut parsed_subquery = parse_tokens(value, options)?;
                     parsed_subquery.sort = Sort::None;
                     restrictions.push(QueryRestriction::Devours(parsed_subquery));
                 }
                 "devouredby" | "devby" | "deby" | "dby" | "db" => {
-                    let mut parsed_subquery = parse_tokens(value)?;
+                    let mut parsed_subquery = parse_tokens(value, options)?;
                     parsed_subquery.sort = Sort::None;
                     restrictions.push(QueryRestriction::DevouredBy(parsed_subquery));
                     // devoured_by = Some(Box::new(parsed_subquery));
@@ -286,20 +461,25 @@ fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
                 par => return Err(Errors::UnknownSubQueryParam(par.to_owned())),
             },
             Token::Not(tokens) => {
-                let mut group = parse_tokens(tokens)?;
+                let mut group = parse_tokens(tokens, options)?;
                 group.sort = Sort::None;
                 restrictions.push(QueryRestriction::Not(group));
             }
             Token::LenientNot(tokens) => {
-                let mut group = parse_tokens(tokens)?;
+                let mut group = parse_tokens(tokens, options)?;
                 group.sort = Sort::None;
                 restrictions.push(QueryRestriction::LenientNot(group));
             }
         }
     }
     let name = name.trim().to_string();
-    if !name.is_empty() {
-        restrictions.push(QueryRestriction::Fuzzy(name.clone()));
+    if options.fuzzy_all_words {
+        restrictions.extend(
+            name.split_whitespace()
+                .map(|word| fuzzy_restriction(word, options.synonyms)),
+        );
+    } else if !name.is_empty() {
+        restrictions.push(fuzzy_restriction(&name, options.synonyms));
     }
     Ok(Query {
         name,
@@ -308,6 +488,55 @@ fn parse_tokens(q: &[Token]) -> Result<Query, Errors> {
     })
 }
 
+/// A fuzzy search for `text`. With `synonyms`, it's an `OR` of every synonym expansion of `text` instead.
+fn fuzzy_restriction(text: &str, synonyms: Option<&SynonymTable>) -> QueryRestriction {
+    let alternative = |restriction| Query {
+        name: String::new(),
+        restrictions: vec![restriction],
+        sort: Sort::None,
+    };
+    synonyms
+        .map_or_else(|| vec![text.to_owned()], |synonyms| synonyms.expand(text))
+        .into_iter()
+        .map(QueryRestriction::Fuzzy)
+        .reduce(|a, b| QueryRestriction::Or(alternative(a), alternative(b)))
+        .unwrap_or_else(|| QueryRestriction::Fuzzy(text.to_owned()))
+}
+
+/// The full name of every property `get_property_from_name` accepts. Shorter aliases are left out.
+pub const PROPERTY_NAMES: &[&str] = &[
+    "id",
+    "name",
+    "flavortext",
+    "description",
+    "type",
+    "set",
+    "cost",
+    "health",
+    "power",
+    "defense",
+    "kin",
+    "function",
+    "ability",
+    "other",
+    "keyword",
+    "keyworddata",
+    "keywordnumber",
+    "is",
+    "missing",
+    "has",
+    "links",
+    "status",
+    "legal",
+    "ids",
+    "desclen",
+    "count",
+    "sort",
+    "sortd",
+    "sortm",
+    "sortmd",
+];
+
 /// # Errors
 /// When `str` is not a valid property query name
 pub fn get_property_from_name(str: &str) -> Result<Properties, Errors> {
@@ -317,6 +546,7 @@ pub fn get_property_from_name(str: &str) -> Result<Properties, Errors> {
         "flavortext" | "flavor" | "ft" => Ok(Properties::StringProperty(Text::FlavorText)),
         "description" | "desc" | "de" => Ok(Properties::StringProperty(Text::Description)),
         "type" | "t" => Ok(Properties::StringProperty(Text::Type)),
+        "set" | "s" => Ok(Properties::StringProperty(Text::Set)),
         "cost" | "c" => Ok(Properties::NumProperty(Number::Cost)),
         "health" | "h" | "hp" => Ok(Properties::NumProperty(Number::Health)),
         "power" | "strength" | "damage" | "p" | "dmg" | "str" => {
@@ -325,30 +555,175 @@ pub fn get_property_from_name(str: &str) -> Result<Properties, Errors> {
         "defense" | "defence" | "def" | "d" => Ok(Properties::NumProperty(Number::Defense)),
         "kin" | "k" => Ok(Properties::ArrayProperty(Array::Kins)),
         "function" | "fun" | "fn" | "f" => Ok(Properties::ArrayProperty(Array::Functions)),
-        "keyword" | "kw" => Ok(Properties::Keywords),
+        "ability" | "abilities" | "ab" => Ok(Properties::ArrayProperty(Array::Abilities)),
+        "other" | "tag" => Ok(Properties::ArrayProperty(Array::Other)),
+        "keyword" | "keywords" | "kw" => Ok(Properties::Keywords),
+        "keyworddata" | "kwdata" | "kwd" => Ok(Properties::KeywordData),
+        "keywordnumber" | "kwnum" => Ok(Properties::KeywordNumber),
+        "is" => Ok(Properties::Is),
+        "missing" => Ok(Properties::Missing),
+        "has" => Ok(Properties::Has),
+        "links" | "link" => Ok(Properties::Links),
+        "status" => Ok(Properties::Status),
+        "legal" | "l" => Ok(Properties::Legal),
+        "ids" => Ok(Properties::Ids),
+        "desclen" | "dl" => Ok(Properties::DescriptionWords),
+        "count" => Ok(Properties::Count),
         "sort" | "so" => Ok(Properties::Sort(Ordering::Ascending)),
         "sortd" | "sod" => Ok(Properties::Sort(Ordering::Descending)),
+        "sortm" | "som" => Ok(Properties::MidpointSort(Ordering::Ascending)),
+        "sortmd" | "somd" => Ok(Properties::MidpointSort(Ordering::Descending)),
         _ => Err(Errors::UnknownStringParam(str.to_owned())),
     }
 }
 
+/// # Errors
+/// When `str` is not a known `is:` flag
+pub fn get_is_restriction(str: &str) -> Result<QueryRestriction, Errors> {
+    match str {
+        "credited" => Ok(QueryRestriction::HasArtist),
+        "imprecise" => Ok(QueryRestriction::HasImpreciseStat),
+        "reprint" => Ok(QueryRestriction::IsReprint),
+        "flavorless" => Ok(QueryRestriction::MissingFlavorText),
+        "aggressive" => Ok(QueryRestriction::IsAggressive),
+        "defensive" => Ok(QueryRestriction::IsDefensive),
+        _ => Err(Errors::UnknownIsParam(str.to_owned())),
+    }
+}
+
+/// # Errors
+/// When `str` is not a property that `missing:` can look for
+pub fn get_missing_restriction(str: &str) -> Result<QueryRestriction, Errors> {
+    match str {
+        "flavortext" | "flavor" | "ft" => Ok(QueryRestriction::MissingFlavorText),
+        _ => Err(Errors::UnknownMissingParam(str.to_owned())),
+    }
+}
+
+/// # Errors
+/// When `str` is not a numeric, text or array property
+pub fn get_has_restriction(str: &str) -> Result<QueryRestriction, Errors> {
+    match get_property_from_name(str) {
+        Ok(Properties::NumProperty(property)) => Ok(QueryRestriction::HasNumProperty(property)),
+        Ok(Properties::StringProperty(property)) => Ok(QueryRestriction::HasTextProperty(property)),
+        Ok(Properties::ArrayProperty(property)) => Ok(QueryRestriction::HasArrayProperty(property)),
+        _ => Err(Errors::UnknownHasParam(str.to_owned())),
+    }
+}
+
 pub enum Properties {
     NumProperty(Number),
     StringProperty(Text),
     ArrayProperty(Array),
     Sort(Ordering),
+    MidpointSort(Ordering),
     Keywords,
+    KeywordData,
+    KeywordNumber,
+    Count,
+    Is,
+    Missing,
+    Has,
+    Links,
+    Status,
+    Legal,
+    Ids,
+    DescriptionWords,
 }
 
 /// A parser for string search queries.
 /// # Errors
 /// Whenever a query cannot be parsed
 pub fn query_parser(q: &str) -> Result<Query, Errors> {
-    let q = tokenize_query(q)?;
-    parse_tokens(&q)
+    query_parser_with_options(q, &ParseOptions::default())
+}
+
+/// Does the same as `query_parser`, but with the given `options` instead of the default ones.
+/// # Errors
+/// Whenever a query cannot be parsed
+pub fn query_parser_with_options(q: &str, options: &ParseOptions) -> Result<Query, Errors> {
+    let q = tokenize_query(q, *options)?;
+    parse_tokens(&q, *options)
+}
+
+/// Parses the `[a,b,c]` part of `ids:[a,b,c]` or `keywords==[a,b,c]`. The brackets are optional.
+fn id_list_parser(s: &str) -> HashSet<String> {
+    let s = s.strip_prefix('[').unwrap_or(s);
+    let s = s.strip_suffix(']').unwrap_or(s);
+    s.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Whether the value of a numeric parameter asks for a variable, like in `cost=X` or `cost:var`. Other letters aren't variables, since `p=h` compares power with health.
+fn is_variable_value(value: &str) -> bool {
+    let value = value.strip_prefix('=').unwrap_or(value);
+    value.eq_ignore_ascii_case("var") || value.eq_ignore_ascii_case("x")
+}
+
+/// Parses the `cost±1` part of `power~cost±1`. A missing tolerance means 0.
+fn within_parser(s: &str) -> Result<(Number, usize), Errors> {
+    let (property, tolerance) = s
+        .split_once('±')
+        .or_else(|| s.split_once("+-"))
+        .unwrap_or((s, "0"));
+    let tolerance = tolerance
+        .parse::<usize>()
+        .map_err(|_| Errors::InvalidComparisonString)?;
+    match get_property_from_name(property)? {
+        Properties::NumProperty(property) => Ok((property, tolerance)),
+        _ => Err(Errors::InvalidComparisonString),
+    }
+}
+
+/// Parses the `>health` part of `power>health` into the operator and the property after it. Without an operator, like in `power:health`, the properties are compared for equality.
+fn field_comparison_parser(s: &str) -> Result<(ComparisonOp, Number), Errors> {
+    let (op, property) = [
+        (">=", ComparisonOp::GreaterThanOrEqual),
+        ("<=", ComparisonOp::LowerThanOrEqual),
+        ("!=", ComparisonOp::NotEqual),
+        (">", ComparisonOp::GreaterThan),
+        ("<", ComparisonOp::LowerThan),
+        ("=", ComparisonOp::Equal),
+    ]
+    .into_iter()
+    .find_map(|(prefix, op)| s.strip_prefix(prefix).map(|property| (op, property)))
+    .unwrap_or((ComparisonOp::Equal, s));
+    match get_property_from_name(property) {
+        Ok(Properties::NumProperty(property)) => Ok((op, property)),
+        _ => Err(Errors::InvalidComparisonString),
+    }
+}
+
+/// Parses the `armor>=2` part of `kwnum:armor>=2`, or the `abilities>=1` part of `count:abilities>=1`, into the name before the operator and the comparison.
+fn named_comparison_parser(s: &str) -> Result<(String, Comparison), Errors> {
+    let start = s
+        .find(['<', '>', '=', '!'])
+        .ok_or(Errors::InvalidComparisonString)?;
+    let (keyword, comparison) = s.split_at(start);
+    Ok((keyword.to_owned(), text_comparison_parser(comparison)?))
+}
+
+/// Parses the `2` and `5` of `2..5`. Ranges whose start is greater than their end are rejected, since they'd admit no numbers.
+fn range_parser(low: &str, high: &str) -> Result<Comparison, Errors> {
+    let low = low
+        .parse::<usize>()
+        .map_err(|_| Errors::InvalidComparisonString)?;
+    let high = high
+        .parse::<usize>()
+        .map_err(|_| Errors::InvalidComparisonString)?;
+    if low > high {
+        return Err(Errors::InvalidComparisonString);
+    }
+    Ok(Comparison::Range { low, high })
 }
 
 pub(crate) fn text_comparison_parser(s: &str) -> Result<Comparison, Errors> {
+    if let Some((low, high)) = s.strip_prefix('=').unwrap_or(s).split_once("..") {
+        return range_parser(low, high);
+    }
     s.parse::<usize>().map_or_else(
         |_| {
             #[allow(clippy::option_if_let_else)]
@@ -383,3 +758,279 @@ pub(crate) fn text_comparison_parser(s: &str) -> Result<Comparison, Errors> {
         |x| Ok(Comparison::Equal(x)),
     )
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        cards::properties::{Array, Number, Text},
+        numbers::Comparison,
+        search::{Errors, Ordering, Query, QueryRestriction, Sort},
+    };
+
+    use super::{
+        get_property_from_name, query_parser, query_parser_with_options, ParseOptions,
+        PROPERTY_NAMES,
+    };
+
+    #[test]
+    fn test_unknown_param_errors() {
+        assert!(matches!(
+            query_parser("powr:3"),
+            Err(Errors::UnknownStringParam(param)) if param == "powr"
+        ));
+        assert!(matches!(
+            query_parser("powr>3"),
+            Err(Errors::UnknownStringParam(param)) if param == "powr"
+        ));
+    }
+
+    #[test]
+    fn test_bare_word_is_fuzzy() {
+        let query = query_parser("power").expect("couldn't parse query");
+        assert_eq!(query.name, "power");
+        assert!(matches!(
+            query.restrictions.as_slice(),
+            [QueryRestriction::Fuzzy(text)] if text == "power"
+        ));
+
+        for word in ["a~b", "~"] {
+            let query = query_parser(word).expect("couldn't parse query");
+            assert!(matches!(
+                query.restrictions.as_slice(),
+                [QueryRestriction::Fuzzy(text)] if text == word
+            ));
+        }
+        assert!(matches!(
+            query_parser("power~cost")
+                .expect("couldn't parse query")
+                .restrictions
+                .as_slice(),
+            [QueryRestriction::Within(Number::Power, Number::Cost, 0)]
+        ));
+    }
+
+    #[test]
+    fn test_regex_size_limit() {
+        assert!(matches!(
+            query_parser("n:/(\\w{100}|[a-z]{50}){100}/"),
+            Err(Errors::RegexErr(regex::Error::CompiledTooBig(_)))
+        ));
+        assert!(query_parser("n:/^(ant|bee)s?$/").is_ok());
+
+        let options = ParseOptions {
+            regex_size_limit: 10_000,
+            ..Default::default()
+        };
+        assert!(matches!(
+            query_parser_with_options("-(kwd:/[a-z]{1000}/)", &options),
+            Err(Errors::RegexErr(regex::Error::CompiledTooBig(_)))
+        ));
+        assert!(query_parser_with_options("kwd:/ant/", &options).is_ok());
+    }
+
+    #[test]
+    fn test_property_names_are_properties() {
+        for name in PROPERTY_NAMES {
+            assert!(get_property_from_name(name).is_ok(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_escaped_quotes() {
+        let query =
+            query_parser(r#"n:"the \"best\" card" k:"back\\slash""#).expect("couldn't parse query");
+        assert!(matches!(
+            query.restrictions.as_slice(),
+            [
+                QueryRestriction::Contains(Text::Name, name),
+                QueryRestriction::Has(Array::Kins, kin),
+            ] if name == r#"the "best" card"# && kin == r"back\slash"
+        ));
+
+        assert!(matches!(
+            query_parser(r#"n:"unfinished \""#),
+            Err(Errors::UnclosedString)
+        ));
+    }
+
+    #[test]
+    fn test_sort_shorthand() {
+        let sort = |query: &str| query_parser(query).expect("couldn't parse query").sort;
+
+        assert_eq!(
+            sort("so:cost"),
+            Sort::Numeric(Number::Cost, Ordering::Ascending)
+        );
+        assert_eq!(
+            sort("k:ant sod:power"),
+            Sort::Numeric(Number::Power, Ordering::Descending)
+        );
+        assert_eq!(
+            sort("sortd:name"),
+            Sort::Alphabet(Text::Name, Ordering::Descending)
+        );
+        assert_eq!(sort("k:ant"), Sort::Fuzzy);
+        assert!(matches!(query_parser("so:kin"), Err(Errors::NotSortable)));
+    }
+
+    #[test]
+    fn test_operator_symbols() {
+        let options = ParseOptions {
+            operator_symbols: true,
+            ..Default::default()
+        };
+        let parse =
+            |query: &str| query_parser_with_options(query, &options).expect("couldn't parse query");
+
+        assert_eq!(parse("cost:2 | cost:3"), parse("cost:2 OR cost:3"));
+        assert_eq!(
+            parse("cost:2 | cost:3 | cost:4"),
+            parse("cost:2 OR cost:3 OR cost:4")
+        );
+        assert_eq!(parse("k:ant & cost:2"), parse("k:ant AND cost:2"));
+        assert_eq!(
+            parse("k:ant & cost:2 | cost:3"),
+            parse("k:ant AND cost:2 | cost:3")
+        );
+        assert!(matches!(
+            query_parser_with_options("| cost:2", &options),
+            Err(Errors::InvalidOr)
+        ));
+
+        let query = query_parser("cost:2 | cost:3").expect("couldn't parse query");
+        assert!(query
+            .restrictions
+            .iter()
+            .any(|restriction| *restriction == QueryRestriction::Fuzzy("|".to_owned())));
+    }
+
+    #[test]
+    fn test_quoted_text_keeps_spaces() {
+        let parsed = |query: &str| {
+            query_parser(query)
+                .expect("couldn't parse query")
+                .restrictions
+        };
+
+        assert_eq!(
+            parsed("name=\"Green Queen\""),
+            [QueryRestriction::Equals(
+                Text::Name,
+                "Green Queen".to_owned()
+            )]
+        );
+        assert_eq!(
+            parsed("name=\"Green   Queen\""),
+            [QueryRestriction::Equals(
+                Text::Name,
+                "Green   Queen".to_owned()
+            )]
+        );
+        assert_eq!(
+            parsed("name:\" Green Queen \""),
+            [QueryRestriction::Contains(
+                Text::Name,
+                " Green Queen ".to_owned()
+            )]
+        );
+        assert_eq!(
+            parsed("n=ant"),
+            [QueryRestriction::Equals(Text::Name, "ant".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_escaped_regex_slashes() {
+        let query = query_parser(r"desc:/a\/b/ n:/\w+\\/").expect("couldn't parse query");
+        assert!(matches!(
+            query.restrictions.as_slice(),
+            [
+                QueryRestriction::Regex(Text::Description, description),
+                QueryRestriction::Regex(Text::Name, name),
+            ] if description.as_str() == "a/b" && name.as_str() == r"\w+\\"
+        ));
+        assert_eq!(
+            query.restrictions[0].to_string(),
+            r"whose Description matches /a\/b/"
+        );
+        // The displayed regex reads back as the same one.
+        let shown = query.restrictions[0].to_string();
+        let reparsed = query_parser(&format!(
+            "desc:{}",
+            &shown["whose Description matches ".len()..]
+        ))
+        .expect("couldn't parse query");
+        assert_eq!(reparsed.restrictions[..], query.restrictions[..1]);
+
+        let query = query_parser(r#"name="she said \"hi\"""#).expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions,
+            [QueryRestriction::Equals(
+                Text::Name,
+                r#"she said "hi""#.to_owned()
+            )]
+        );
+        assert_eq!(
+            query.restrictions[0].to_string(),
+            r#"whose Name is "she said \"hi\"""#
+        );
+        let shown = query.restrictions[0].to_string();
+        let reparsed = query_parser(&format!("name={}", &shown["whose Name is ".len()..]))
+            .expect("couldn't parse query");
+        assert_eq!(reparsed.restrictions, query.restrictions);
+
+        let query = query_parser(r#"n:"back\\slash""#).expect("couldn't parse query");
+        assert_eq!(
+            query.restrictions[0].to_string(),
+            r#"whose Name contains "back\\slash""#
+        );
+
+        assert!(matches!(
+            query_parser(r"desc:/a\"),
+            Err(Errors::UnclosedRegex)
+        ));
+    }
+
+    #[test]
+    fn test_and_keyword() {
+        let parsed = |query: &str| {
+            query_parser(query)
+                .expect("couldn't parse query")
+                .restrictions
+        };
+        let cost = QueryRestriction::Comparison(Number::Cost, Comparison::Equal(2));
+        let power = QueryRestriction::Comparison(Number::Power, Comparison::Equal(3));
+        let kin = QueryRestriction::Has(Array::Kins, "ant".to_owned());
+        let group = |restrictions: Vec<QueryRestriction>| Query {
+            name: String::new(),
+            restrictions,
+            sort: Sort::None,
+        };
+
+        assert_eq!(
+            parsed("cost=2 AND power=3"),
+            [QueryRestriction::Group(group(vec![
+                cost.clone(),
+                power.clone()
+            ]))]
+        );
+        assert_eq!(
+            parsed("k:ant OR cost=2 AND power=3"),
+            [QueryRestriction::Group(group(vec![
+                QueryRestriction::Or(group(vec![kin.clone()]), group(vec![cost.clone()])),
+                power.clone(),
+            ]))]
+        );
+        assert_eq!(
+            parsed("k:ant OR (cost=2 AND power=3)"),
+            [QueryRestriction::Or(
+                group(vec![kin]),
+                group(vec![QueryRestriction::Group(group(vec![
+                    QueryRestriction::Group(group(vec![cost, power]))
+                ]))])
+            )]
+        );
+        assert!(matches!(query_parser("AND cost=2"), Err(Errors::InvalidOr)));
+        assert!(matches!(query_parser("cost=2 AND"), Err(Errors::InvalidOr)));
+    }
+}